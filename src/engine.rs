@@ -0,0 +1,381 @@
+use std::{fmt::Display, io, path::Path};
+
+use eframe::{
+    egui::Ui,
+    epaint::{vec2, Color32, Pos2},
+};
+use thiserror::Error;
+
+use crate::grid::{
+    rle, Automaton, Boundary, CellBounds, GridDrawSettings, GridTransform, LifeCell, Periodicity,
+    Rule, TextData, MAX_PATTERN_CELLS,
+};
+use crate::hash_life::macrocell::{self, Macrocell};
+
+mod image;
+
+pub use image::{CellImage, ImageExportError};
+
+#[derive(Debug, Error)]
+pub enum InvalidEngine {
+    #[error("Unbounded engines can not run rules with B0 because empty space would turn alive")]
+    BirthWithoutNeighbours,
+    #[error("Unbounded engines can only run rules with alive and dead cells, not {0}")]
+    GenerationsRule(Rule),
+    #[error("The sparse engine can only run rules which count neighbours, not {0}")]
+    NonTotalisticRule(Rule),
+    #[error("Only the grid engine can run Larger than Life rules like {0}")]
+    LargerThanLife(Rule),
+    #[error("Only the grid engine can run hexagonal rules like {0}")]
+    HexagonalRule(Rule),
+    #[error("Only the grid engine can run {0}")]
+    HandWrittenAutomaton(&'static str),
+    #[error("Macrocell files can only be run by the HashLife engine with a life like rule")]
+    MacrocellFile,
+}
+
+/// Which engine calculates the generations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EngineKind {
+    /// Fixed size grid from the loaded file with the selected boundary.
+    #[default]
+    Grid,
+    /// Memoized quadtree on an unbounded plane for very long runs.
+    HashLife,
+    /// Chunks with alive cells on an unbounded plane which grow and shrink with the pattern.
+    Sparse,
+}
+
+impl EngineKind {
+    pub const ALL: [EngineKind; 3] = [EngineKind::Grid, EngineKind::HashLife, EngineKind::Sparse];
+}
+
+impl Display for EngineKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Grid => write!(f, "Grid"),
+            Self::HashLife => write!(f, "HashLife"),
+            Self::Sparse => write!(f, "Sparse"),
+        }
+    }
+}
+
+/// Calculates generations of a pattern and draws them.
+/// Cells are addressed by row and column, which may be negative for unbounded engines.
+pub trait Engine: Send {
+    fn kind(&self) -> EngineKind;
+
+    /// Copy which is handed to the ui to be drawn.
+    fn snapshot(&self) -> Box<dyn Engine>;
+
+    fn tick(&mut self);
+
+    /// Compact copy for the history, which leaves out buffers and caches that
+    /// [`Engine::restore_keyframe`] calculates again.
+    fn keyframe(&self) -> Box<dyn Engine> {
+        self.snapshot()
+    }
+
+    /// Rebuilds what a keyframe left out, so that it can step again.
+    fn restore_keyframe(&mut self) {}
+
+    /// Rough number of bytes a keyframe of the current generation takes.
+    fn keyframe_bytes(&self) -> usize;
+
+    /// Advances by 2 to the power of the exponent generations at once.
+    fn step_pow2(&mut self, exponent: u32) {
+        for _ in 0..(1u64 << exponent) {
+            self.tick();
+        }
+    }
+
+    /// Largest exponent for [`Engine::step_pow2`] which is still fast enough.
+    fn max_step_exponent(&self) -> u32;
+
+    fn passed_tick(&self) -> u64;
+
+    fn population(&self) -> u64;
+
+    fn cell_at(&self, y: i64, x: i64) -> LifeCell;
+
+    /// Changes a single cell by hand. Counts as an edit if the cell changed.
+    fn set_cell_at(&mut self, y: i64, x: i64, cell: LifeCell);
+
+    fn edited_cells(&self) -> usize;
+
+    /// Area with all cells for bounded engines or the smallest area with all alive cells.
+    fn bounds(&self) -> CellBounds;
+
+    fn draw_at(&self, ui: &mut Ui, transform: GridTransform);
+
+    fn drawing(&self) -> &GridDrawSettings;
+
+    fn set_drawing(&mut self, drawing: GridDrawSettings);
+
+    /// Colour the cell is drawn with, which may show more than its state.
+    fn cell_color(&self, y: i64, x: i64) -> Color32 {
+        self.drawing().color_for(self.cell_at(y, x))
+    }
+
+    /// True if cells can be coloured by their age or by the trails of died cells.
+    fn tracks_ages(&self) -> bool {
+        false
+    }
+
+    /// True if the engine can look for repetitions of the whole pattern after every generation.
+    fn detects_periods(&self) -> bool {
+        false
+    }
+
+    /// Starts or stops looking for repetitions, which takes time on every generation.
+    fn set_period_detection(&mut self, _enabled: bool) {}
+
+    fn is_detecting_periods(&self) -> bool {
+        false
+    }
+
+    /// Repetition of the whole pattern which the current generation completed.
+    fn periodicity(&self) -> Option<Periodicity> {
+        None
+    }
+
+    fn rule(&self) -> Rule;
+
+    /// None for engines without any edges.
+    fn boundary(&self) -> Option<Boundary>;
+
+    /// Hand written automaton which is run instead of the rule.
+    fn automaton(&self) -> Option<&dyn Automaton> {
+        None
+    }
+
+    /// Name of the automaton or the rule in B/S notation.
+    fn rule_name(&self) -> String {
+        match self.automaton() {
+            Some(automaton) => automaton.name().to_owned(),
+            None => self.rule().to_string(),
+        }
+    }
+
+    /// Switches between calculating a generation on one thread or on all cores.
+    fn set_parallel(&mut self, _parallel: bool) {}
+
+    fn is_parallel(&self) -> bool {
+        false
+    }
+
+    /// Returns the coordinates of the cell under the given screen position.
+    fn cell_under(&self, transform: GridTransform, position: Pos2) -> Option<(i64, i64)> {
+        let (y, mut x) = transform.cell_at_position(position);
+        // Odd rows of hexagonal grids are shifted half a cell to the right.
+        if self.rule().is_hexagonal() && y.rem_euclid(2) == 1 {
+            x = transform
+                .cell_at_position(position - vec2(transform.cell_size / 2., 0.))
+                .1;
+        }
+        match self.boundary() {
+            Some(_) if !self.bounds().contains(y, x) => None,
+            _ => Some((y, x)),
+        }
+    }
+
+    fn unscaled_cell_size(&self) -> f32 {
+        self.drawing().cell_size as f32
+    }
+
+    /// Writes the current generation to a file which can be loaded again.
+    /// Files with the extension rle are written in the RLE format, files with the extension mc
+    /// in the Macrocell format and all others as plain text with the given char for every state.
+    fn save(&self, path: &Path, state_chars: &[char]) -> io::Result<()> {
+        let content = if TextData::is_rle_file(path) {
+            if let Some(automaton) = self.automaton() {
+                let message = format!("RLE files can not hold the states of {}", automaton.name());
+                return Err(io::Error::new(io::ErrorKind::Unsupported, message));
+            }
+            self.to_rle()?
+        } else if Macrocell::is_macrocell_file(path) {
+            if let Some(automaton) = self.automaton() {
+                let message = format!(
+                    "Macrocell files can not hold the states of {}",
+                    automaton.name()
+                );
+                return Err(io::Error::new(io::ErrorKind::Unsupported, message));
+            }
+            if self.rule().is_generations() {
+                let message = "Macrocell files can not hold the dying states of generations rules";
+                return Err(io::Error::new(io::ErrorKind::Unsupported, message));
+            }
+            if self.rule().is_hexagonal() {
+                let message = "Macrocell files can not hold hexagonal patterns";
+                return Err(io::Error::new(io::ErrorKind::Unsupported, message));
+            }
+            self.to_macrocell()
+        } else {
+            self.to_plain_text(state_chars)?
+        };
+        std::fs::write(path, content)
+    }
+
+    /// Writes the current generation as a PNG image with squares of `cell_size` pixels.
+    fn export_image(
+        &self,
+        path: &Path,
+        cell_size: u32,
+        grid_lines: bool,
+    ) -> Result<(), ImageExportError> {
+        CellImage::render(self, cell_size, grid_lines)?.write_png(path)
+    }
+
+    /// States without a char in `state_chars` are written with the char of state 0.
+    fn to_plain_text(&self, state_chars: &[char]) -> io::Result<String> {
+        let bounds = self.bounds();
+        check_text_size(self, bounds.height, bounds.width)?;
+        let mut output = String::with_capacity((bounds.height * (bounds.width + 1)) as usize);
+        for y in bounds.top..bounds.top + bounds.height as i64 {
+            for x in bounds.left..bounds.left + bounds.width as i64 {
+                let state = self.cell_at(y, x).state() as usize;
+                output.push(*state_chars.get(state).unwrap_or(&state_chars[0]));
+            }
+            output.push('\n');
+        }
+        Ok(output)
+    }
+
+    fn to_rle(&self) -> io::Result<String> {
+        let bounds = self.bounds();
+        if self.rule().is_hexagonal() {
+            return to_sheared_rle(self);
+        }
+        check_text_size(self, bounds.height, bounds.width)?;
+        Ok(rle::encode(
+            bounds.width as usize,
+            bounds.height as usize,
+            self.rule(),
+            |y, x| {
+                self.cell_at(bounds.top + y as i64, bounds.left + x as i64)
+                    .state()
+            },
+        ))
+    }
+
+    /// Builds a quadtree of the cells to write it in the Macrocell format.
+    fn to_macrocell(&self) -> String {
+        macrocell::encode_cells(self.bounds(), |y, x| self.cell_at(y, x), self.rule())
+    }
+}
+
+/// Writes a hexagonal pattern like Golly by shifting every row half a cell further
+/// to the right than the one above it. Dead columns on the left and right are left out.
+fn to_sheared_rle<E: Engine + ?Sized>(engine: &E) -> io::Result<String> {
+    let bounds = engine.bounds();
+    let rows = bounds.top..bounds.top + bounds.height as i64;
+    let columns = bounds.left..bounds.left + bounds.width as i64;
+    let shift = |y: i64| (y + 1).div_euclid(2);
+    let state_at = |y: i64, x: i64| engine.cell_at(y, x).state();
+
+    let sheared_columns = rows.clone().flat_map(|y| {
+        columns
+            .clone()
+            .filter(move |&x| state_at(y, x) != 0)
+            .map(move |x| x + shift(y))
+    });
+    let (left, right) = sheared_columns
+        .fold(None, |range: Option<(i64, i64)>, x| match range {
+            Some((left, right)) => Some((left.min(x), right.max(x))),
+            None => Some((x, x)),
+        })
+        .unwrap_or((0, 0));
+
+    let width = (right - left + 1) as u64;
+    check_text_size(engine, bounds.height, width)?;
+    Ok(rle::encode(
+        (right - left + 1) as usize,
+        bounds.height as usize,
+        engine.rule(),
+        |y, x| {
+            let y = bounds.top + y as i64;
+            let x = left + x as i64 - shift(y);
+            if columns.contains(&x) {
+                state_at(y, x)
+            } else {
+                0
+            }
+        },
+    ))
+}
+
+/// Text formats write every cell of the bounding box, which takes too long and too much memory
+/// for the huge patterns of unbounded engines. Those can still be saved as Macrocell.
+fn check_text_size<E: Engine + ?Sized>(engine: &E, height: u64, width: u64) -> io::Result<()> {
+    let cells = height.checked_mul(width.saturating_add(1));
+    if cells.is_some_and(|cells| cells <= MAX_PATTERN_CELLS as u64) {
+        return Ok(());
+    }
+    let mut message = format!(
+        "Pattern of {} x {} cells is too large for a text file",
+        width, height
+    );
+    if engine.automaton().is_none()
+        && !engine.rule().is_generations()
+        && !engine.rule().is_hexagonal()
+    {
+        message.push_str(", save it as .mc instead");
+    }
+    Err(io::Error::new(io::ErrorKind::InvalidInput, message))
+}
+
+impl Clone for Box<dyn Engine> {
+    fn clone(&self) -> Self {
+        self.snapshot()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{grid::Grid, hash_life::HashLife};
+
+    fn hexagonal_grid(text: &str) -> Grid {
+        let text = rle::parse(text).expect("Pattern is valid");
+        let rule = text.rule().expect("Pattern has a rule");
+        Grid::new(text, rule, Boundary::Plane, GridDrawSettings::default())
+    }
+
+    /// Alive cells with their columns counted from the leftmost alive cell.
+    fn alive_cells(engine: &dyn Engine) -> Vec<(i64, i64)> {
+        let bounds = engine.bounds();
+        let alive: Vec<(i64, i64)> = (bounds.top..bounds.top + bounds.height as i64)
+            .flat_map(|y| (bounds.left..bounds.left + bounds.width as i64).map(move |x| (y, x)))
+            .filter(|&(y, x)| engine.cell_at(y, x) == LifeCell::Alive)
+            .collect();
+        let left = alive.iter().map(|&(_, x)| x).min().unwrap_or(0);
+        alive.into_iter().map(|(y, x)| (y, x - left)).collect()
+    }
+
+    #[test]
+    fn hexagonal_patterns_round_trip_through_sheared_rle() {
+        let text = "x = 7, y = 6, rule = B2/S34H\n3bo$obo2bo$4b3o$o$bobo$6bo!\n";
+        let original = hexagonal_grid(text);
+        let sheared = original.to_rle().unwrap();
+        let reloaded = hexagonal_grid(&sheared);
+
+        assert_eq!(alive_cells(&reloaded), alive_cells(&original));
+        assert_eq!(reloaded.to_rle().unwrap(), sheared);
+        // Golly's layout of the rows is written back just like it was read.
+        assert_eq!(sheared, text);
+    }
+
+    #[test]
+    fn huge_patterns_are_not_written_as_text() {
+        let text = rle::parse("x = 1, y = 1\no!\n").unwrap();
+        let mut hash_life =
+            HashLife::new(&text, Rule::conway(), GridDrawSettings::default()).unwrap();
+        hash_life.set_cell_at(1 << 20, 1 << 20, LifeCell::Alive);
+
+        let error = hash_life.to_rle().expect_err("Pattern is too large");
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(error.to_string().contains(".mc"));
+        assert!(hash_life.to_plain_text(&['.', 'O']).is_err());
+        assert!(hash_life.to_macrocell().starts_with("[M2]"));
+    }
+}
@@ -0,0 +1,225 @@
+use std::{fs::File, io, io::BufWriter, path::Path};
+
+use eframe::epaint::Color32;
+use thiserror::Error;
+
+use super::Engine;
+use crate::grid::LifeCell;
+
+/// Larger images would take gigabytes of memory to draw.
+const MAX_IMAGE_PIXELS: u64 = 1 << 28;
+const BYTES_PER_PIXEL: usize = 3;
+
+#[derive(Debug, Error)]
+pub enum ImageExportError {
+    #[error("There are no cells to draw")]
+    NoCells,
+    #[error("Image of {width} x {height} pixels is too large, choose a smaller cell size")]
+    TooLarge { width: u64, height: u64 },
+    #[error("Image could not be written: {0}")]
+    IoError(#[from] io::Error),
+    #[error("Image could not be encoded: {0}")]
+    EncodingError(#[from] png::EncodingError),
+}
+
+/// Pixels of the cells of an engine drawn on the cpu, so no graphics card is needed.
+pub struct CellImage {
+    width: u32,
+    height: u32,
+    /// Red, green and blue of every pixel row by row.
+    pixels: Vec<u8>,
+}
+
+impl CellImage {
+    /// Draws every cell in the bounds of the engine as a square of `cell_size` pixels
+    /// in the colours of its draw settings. Grid lines are one pixel wide and drawn
+    /// over the top and left edge of every cell, with one more line at the bottom and right.
+    /// Odd rows of hexagonal grids are shifted half a cell to the right.
+    pub fn render<E: Engine + ?Sized>(
+        engine: &E,
+        cell_size: u32,
+        grid_lines: bool,
+    ) -> Result<Self, ImageExportError> {
+        let bounds = engine.bounds();
+        if bounds.height == 0 || bounds.width == 0 {
+            return Err(ImageExportError::NoCells);
+        }
+        // Cells of a single pixel would be covered completely by grid lines.
+        let cell_size = cell_size.max(1 + u32::from(grid_lines));
+        let hexagonal = engine.rule().is_hexagonal();
+        let shift = if hexagonal { cell_size / 2 } else { 0 };
+        let line = u64::from(grid_lines);
+        let width = bounds.width * u64::from(cell_size) + u64::from(shift) + line;
+        let height = bounds.height * u64::from(cell_size) + line;
+        if width.saturating_mul(height) > MAX_IMAGE_PIXELS {
+            return Err(ImageExportError::TooLarge { width, height });
+        }
+        let (width, height) = (width as u32, height as u32);
+
+        let drawing = engine.drawing();
+        let background = drawing.color_for(LifeCell::Dead);
+        let mut image = Self {
+            width,
+            height,
+            pixels: Vec::with_capacity(width as usize * height as usize * BYTES_PER_PIXEL),
+        };
+        let mut row_pixels = vec![background; width as usize];
+        for row in 0..bounds.height as i64 {
+            let y = bounds.top + row;
+            let row_shift = if hexagonal && y.rem_euclid(2) == 1 {
+                shift as usize
+            } else {
+                0
+            };
+
+            row_pixels.fill(background);
+            for column in 0..bounds.width as i64 {
+                let color = engine.cell_color(y, bounds.left + column);
+                let start = row_shift + column as usize * cell_size as usize;
+                row_pixels[start..start + cell_size as usize].fill(color);
+            }
+            if grid_lines {
+                for column in 0..=bounds.width as usize {
+                    row_pixels[row_shift + column * cell_size as usize] = drawing.stroke_color;
+                }
+                image.push_line(drawing.stroke_color);
+            }
+
+            let rows = cell_size as usize - usize::from(grid_lines);
+            for _ in 0..rows {
+                image.push_row(&row_pixels);
+            }
+        }
+        if grid_lines {
+            image.push_line(drawing.stroke_color);
+        }
+        Ok(image)
+    }
+
+    pub fn write_png(&self, path: &Path) -> Result<(), ImageExportError> {
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(file, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+        writer.finish()?;
+        Ok(())
+    }
+
+    fn push_row(&mut self, row: &[Color32]) {
+        for color in row {
+            self.pixels
+                .extend_from_slice(&[color.r(), color.g(), color.b()]);
+        }
+    }
+
+    /// Horizontal grid line over the whole width of the image.
+    fn push_line(&mut self, color: Color32) {
+        for _ in 0..self.width {
+            self.pixels
+                .extend_from_slice(&[color.r(), color.g(), color.b()]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::{rle, Boundary, Grid, GridDrawSettings};
+
+    fn grid(text: &str) -> Grid {
+        let text = rle::parse(text).expect("Pattern is valid");
+        let rule = text.rule().expect("Pattern has a rule");
+        Grid::new(text, rule, Boundary::Plane, GridDrawSettings::default())
+    }
+
+    fn pixel(image: &CellImage, y: u32, x: u32) -> Color32 {
+        let start = (y as usize * image.width as usize + x as usize) * BYTES_PER_PIXEL;
+        let [r, g, b] = [0, 1, 2].map(|channel| image.pixels[start + channel]);
+        Color32::from_rgb(r, g, b)
+    }
+
+    #[test]
+    fn draws_every_cell_as_a_square_of_its_colour() {
+        let brain = grid("x = 3, y = 2, rule = /2/3\nA.B$.A!\n");
+        let drawing = brain.drawing();
+        let image = CellImage::render(&brain, 4, false).unwrap();
+        assert_eq!((image.width, image.height), (12, 8));
+        assert_eq!(image.pixels.len(), 12 * 8 * BYTES_PER_PIXEL);
+
+        let dying = brain.cell_at(0, 2);
+        assert!(matches!(dying, LifeCell::Dying(_)));
+        for (y, x, cell) in [
+            (0, 0, LifeCell::Alive),
+            (0, 1, LifeCell::Dead),
+            (0, 2, dying),
+            (1, 1, LifeCell::Alive),
+        ] {
+            let color = drawing.color_for(cell);
+            for (dy, dx) in [(0, 0), (3, 3), (1, 2)] {
+                assert_eq!(pixel(&image, y * 4 + dy, x * 4 + dx), color, "{:?}", cell);
+            }
+        }
+        assert_ne!(drawing.color_for(dying), drawing.color_for(LifeCell::Alive));
+    }
+
+    #[test]
+    fn grid_lines_frame_every_cell() {
+        let brain = grid("x = 3, y = 2, rule = /2/3\nA.B$.A!\n");
+        let stroke = brain.drawing().stroke_color;
+        let image = CellImage::render(&brain, 4, true).unwrap();
+        assert_eq!((image.width, image.height), (13, 9));
+        for line in [0, 4, 8] {
+            assert!((0..image.width).all(|x| pixel(&image, line, x) == stroke));
+        }
+        for line in [0, 4, 8, 12] {
+            assert!((0..image.height).all(|y| pixel(&image, y, line) == stroke));
+        }
+        let alive = brain.drawing().color_for(LifeCell::Alive);
+        assert_eq!(pixel(&image, 1, 1), alive);
+
+        // Single pixels would only show the lines, so cells are drawn at least two pixels wide.
+        let smallest = CellImage::render(&brain, 1, true).unwrap();
+        assert_eq!((smallest.width, smallest.height), (7, 5));
+    }
+
+    #[test]
+    fn shifts_odd_rows_of_hexagonal_patterns() {
+        let hexagonal = grid("x = 2, y = 2, rule = B2/S34H\n2o$2o!\n");
+        let bounds = hexagonal.bounds();
+        let image = CellImage::render(&hexagonal, 4, false).unwrap();
+        assert_eq!(image.width as u64, bounds.width * 4 + 2);
+
+        let background = hexagonal.drawing().color_for(LifeCell::Dead);
+        for row in 0..bounds.height as i64 {
+            let y = bounds.top + row;
+            let shift = if y.rem_euclid(2) == 1 { 2 } else { 0 };
+            let pixel_row = row as u32 * 4;
+            for x in 0..shift {
+                assert_eq!(pixel(&image, pixel_row, x), background);
+            }
+            for column in 0..bounds.width as i64 {
+                let color = hexagonal
+                    .drawing()
+                    .color_for(hexagonal.cell_at(y, bounds.left + column));
+                let x = shift + column as u32 * 4;
+                assert_eq!(pixel(&image, pixel_row, x), color);
+                assert_eq!(pixel(&image, pixel_row, x + 3), color);
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_images_larger_than_the_limit() {
+        let block = grid("x = 100, y = 100, rule = B3/S23\n2o$2o98$99bo!\n");
+        assert!(matches!(
+            CellImage::render(&block, 1 << 10, true),
+            Err(ImageExportError::TooLarge {
+                width: 102_401,
+                height: 102_401
+            })
+        ));
+        assert!(CellImage::render(&block, 2, false).is_ok());
+    }
+}
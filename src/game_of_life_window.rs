@@ -17,7 +17,7 @@ impl eframe::App for GameOfLifeWindow {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         egui::CentralPanel::default().show(ctx, |ui| match &mut self.view {
             CurrentView::Game(game_view) => {
-                game_view.tick_if_up();
+                game_view.receive_snapshot();
                 if let Some(open_view) = game_view.draw(ui) {
                     self.view = CurrentView::Open(open_view);
                 }
@@ -1,44 +1,153 @@
-use eframe::egui::Ui;
-use eframe::epaint::Pos2;
+use std::io;
+use std::path::{Path, PathBuf};
 
-use crate::constans::*;
-use crate::grid::Grid;
+use eframe::egui::{Sense, Ui};
+use egui_file::FileDialog;
+
+use crate::engine::{Engine, ImageExportError};
+use crate::grid::{GridDrawSettings, LifeCell};
 use crate::open_view::{GatheredOpenViewData, OpenView};
-use crate::timer::Timer;
+mod camera;
 mod drawing;
+mod editing;
+mod history;
+mod worker;
+
+use camera::Camera;
+use history::HistoryRange;
+use worker::{Command, Worker};
 
 pub struct GameView {
-    grid: Grid,
-    tick_timer: Timer,
+    /// Latest copy of the engine which is owned by the worker.
+    grid: Box<dyn Engine>,
+    worker: Worker,
     is_paused: bool,
     previous_view: GatheredOpenViewData,
+    save_file_dialog: Option<FileDialog>,
+    last_save: Option<Result<PathBuf, io::Error>>,
+    export_file_dialog: Option<FileDialog>,
+    last_export: Option<Result<PathBuf, ImageExportError>>,
+    /// Pixels per cell of exported images.
+    image_cell_size: u32,
+    image_grid_lines: bool,
+    paint_stroke: Option<(LifeCell, (i64, i64))>,
+    /// State painted by dragging over dead cells.
+    brush: LifeCell,
+    edited_grid: Option<Box<dyn Engine>>,
+    reset_to_edited: bool,
+    camera: Camera,
+    fit_requested: bool,
+    /// Fits the camera to the alive cells on every frame.
+    follow_pattern: bool,
+    step_exponent: u32,
+    /// Pauses as soon as the whole pattern is found to repeat.
+    pause_on_repetition: bool,
+    /// Generations the worker can go back to.
+    history: HistoryRange,
 }
 
 impl GameView {
     pub fn new(previous_view: GatheredOpenViewData) -> Self {
-        let mut slf = Self {
-            grid: previous_view.clone_game(),
-            tick_timer: Timer::new(previous_view.time_interval.into()),
+        let grid = previous_view.clone_game();
+        let image_cell_size = grid.drawing().cell_size as u32;
+        // The worker starts paused with its own copy, so there is nothing to reset yet.
+        let worker = Worker::spawn(grid.clone(), previous_view.time_interval.into());
+        Self {
+            grid,
+            worker,
             previous_view,
-            is_paused: false,
+            is_paused: true,
+            save_file_dialog: None,
+            last_save: None,
+            export_file_dialog: None,
+            last_export: None,
+            image_cell_size,
+            image_grid_lines: false,
+            paint_stroke: None,
+            brush: LifeCell::Alive,
+            edited_grid: None,
+            reset_to_edited: false,
+            camera: Camera::default(),
+            fit_requested: false,
+            follow_pattern: false,
+            step_exponent: 0,
+            pause_on_repetition: false,
+            history: HistoryRange::default(),
+        }
+    }
+    pub fn reset(&mut self) {
+        self.pause();
+        let parallel = self.grid.is_parallel();
+        let detect_periods = self.grid.is_detecting_periods();
+        let drawing = self.grid.drawing().clone();
+        self.grid = match (&self.edited_grid, self.reset_to_edited) {
+            (Some(edited), true) => edited.clone(),
+            _ => self.previous_view.clone_game(),
         };
+        self.grid.set_parallel(parallel);
+        self.grid.set_period_detection(detect_periods);
+        self.grid.set_drawing(drawing);
+        self.worker.send(Command::Replace(self.grid.clone()));
+    }
+
+    /// Every step and tick advances by 2 to the power of the exponent generations.
+    pub fn set_step_exponent(&mut self, exponent: u32) {
+        self.step_exponent = exponent;
+        self.worker.send(Command::SetStepExponent(exponent));
+    }
 
-        slf.reset();
+    pub fn step(&mut self) {
+        self.worker.send(Command::Step);
+    }
 
-        slf
+    pub fn step_back(&mut self) {
+        self.worker.send(Command::Previous);
     }
-    pub fn reset(&mut self) {
+
+    /// Shows the generation at the given position of the history.
+    /// Stepping or resuming continues from there and forgets the generations after it.
+    pub fn seek(&mut self, position: usize) {
         self.pause();
-        self.tick_timer.reset();
-        self.grid = self.previous_view.clone_game();
+        self.history.current = position;
+        self.worker.send(Command::Seek(position));
+    }
+
+    pub fn set_parallel(&mut self, parallel: bool) {
+        self.grid.set_parallel(parallel);
+        self.worker.send(Command::SetParallel(parallel));
+    }
+
+    /// Looking for repetitions takes time on every generation, so it is only done on request.
+    pub fn set_period_detection(&mut self, enabled: bool) {
+        self.grid.set_period_detection(enabled);
+        self.worker.send(Command::SetPeriodDetection(enabled));
+    }
+
+    /// Changes how cells are coloured in the shown copy and in the grid of the worker.
+    pub fn set_drawing(&mut self, drawing: GridDrawSettings) {
+        self.grid.set_drawing(drawing.clone());
+        self.worker.send(Command::SetDrawing(drawing));
+    }
+
+    /// Changes the cell in the shown copy right away and in the grid of the worker.
+    pub fn set_cell(&mut self, y: i64, x: i64, cell: LifeCell) {
+        self.grid.set_cell_at(y, x, cell);
+        self.worker.send(Command::SetCell(y, x, cell));
+    }
+
+    /// Keeps the grid as it is after editing so a reset can return to it.
+    /// Also adds it to the history, so the edit can be undone by going back.
+    pub fn remember_edited_state(&mut self) {
+        self.edited_grid = Some(self.grid.clone());
+        self.worker.send(Command::RecordEdit);
     }
 
     pub fn pause(&mut self) {
-        self.tick_timer.pause();
+        self.worker.send(Command::Pause);
         self.is_paused = true;
     }
     pub fn resume(&mut self) {
-        self.tick_timer.resume();
+        self.worker.send(Command::Resume);
         self.is_paused = false;
     }
 
@@ -54,10 +163,32 @@ impl GameView {
         }
     }
 
-    pub fn tick_if_up(&mut self) {
-        if self.tick_timer.is_up() {
-            self.tick_timer.reset_time();
-            self.grid.tick();
+    pub fn save_to(&mut self, path: &Path) {
+        let state_chars = self.previous_view.state_chars();
+        self.last_save = Some(self.grid.save(path, &state_chars).map(|_| path.to_owned()));
+    }
+
+    /// Draws the shown generation into a PNG image, adding the extension png if it is missing.
+    pub fn export_image_to(&mut self, path: &Path) {
+        let path = match path.extension() {
+            Some(_) => path.to_owned(),
+            None => path.with_extension("png"),
+        };
+        let exported = self
+            .grid
+            .export_image(&path, self.image_cell_size, self.image_grid_lines);
+        self.last_export = Some(exported.map(|_| path));
+    }
+
+    pub fn receive_snapshot(&mut self) {
+        if let Some(snapshot) = self.worker.latest_snapshot() {
+            let repetition_found =
+                self.grid.periodicity().is_none() && snapshot.engine.periodicity().is_some();
+            self.grid = snapshot.engine;
+            self.history = snapshot.history;
+            if repetition_found && self.pause_on_repetition && !self.is_paused {
+                self.pause();
+            }
         }
     }
 
@@ -65,13 +196,27 @@ impl GameView {
         drawing::draw_stats(self, ui);
         let to_return = drawing::draw_buttons(self, ui);
 
-        let y_offset = ui.available_rect_before_wrap().min.y;
-        let start = Pos2 {
-            x: MARGIN,
-            y: y_offset,
-        };
+        let canvas = ui.available_rect_before_wrap();
+        let response = ui.allocate_rect(canvas, Sense::click_and_drag());
+        if std::mem::take(&mut self.fit_requested) || self.follow_pattern {
+            let cell_size = self.grid.unscaled_cell_size();
+            self.camera
+                .fit(canvas, self.grid.bounds().unscaled_rect(cell_size));
+        }
+        let navigating = self.camera.navigate_on_input(ui, &response, canvas);
+        if navigating {
+            self.follow_pattern = false;
+        }
+
+        let transform = self
+            .camera
+            .transform(canvas, self.grid.unscaled_cell_size());
+        if self.is_paused && !navigating {
+            editing::edit_on_input(self, &response, transform);
+        }
 
-        self.grid.draw_at(ui, start);
+        ui.set_clip_rect(canvas);
+        self.grid.draw_at(ui, transform);
 
         to_return
     }
@@ -0,0 +1,90 @@
+use eframe::{
+    egui::{Key, PointerButton, Response, Ui},
+    epaint::{Pos2, Rect, Vec2},
+};
+
+use crate::{constans::MARGIN, grid::GridTransform};
+
+const MIN_ZOOM: f32 = 0.02;
+const MAX_ZOOM: f32 = 20.;
+const SCROLL_ZOOM_SPEED: f32 = 0.002;
+
+/// Part of the grid which is visible on the canvas.
+pub struct Camera {
+    /// Position of the grid origin relative to the top left corner of the canvas.
+    offset: Vec2,
+    zoom: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            offset: Vec2::new(MARGIN, 0.),
+            zoom: 1.,
+        }
+    }
+}
+
+impl Camera {
+    pub fn transform(&self, canvas: Rect, unscaled_cell_size: f32) -> GridTransform {
+        GridTransform {
+            origin: canvas.min + self.offset,
+            cell_size: unscaled_cell_size * self.zoom,
+        }
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    pub fn pan(&mut self, delta: Vec2) {
+        self.offset += delta;
+    }
+
+    /// Zooms by the factor while the point under the cursor stays in place.
+    pub fn zoom_at(&mut self, canvas: Rect, cursor: Pos2, factor: f32) {
+        let new_zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        let ratio = new_zoom / self.zoom;
+        let origin = canvas.min + self.offset;
+        let new_origin = cursor - (cursor - origin) * ratio;
+
+        self.offset = new_origin - canvas.min;
+        self.zoom = new_zoom;
+    }
+
+    /// Zooms and centres so the given area of the grid is visible on the canvas.
+    /// The area is measured in pixels without zoom from the origin of the grid.
+    pub fn fit(&mut self, canvas: Rect, unscaled_area: Rect) {
+        if unscaled_area.width() <= 0. || unscaled_area.height() <= 0. {
+            return;
+        }
+        let zoom = (canvas.width() / unscaled_area.width())
+            .min(canvas.height() / unscaled_area.height())
+            .clamp(MIN_ZOOM, MAX_ZOOM);
+
+        self.zoom = zoom;
+        self.offset =
+            (canvas.size() - unscaled_area.size() * zoom) / 2. - unscaled_area.min.to_vec2() * zoom;
+    }
+
+    /// Zooms with the scroll wheel and pans with a middle drag or a drag while space is held.
+    /// Returns true if the pointer is used for navigation and should not edit cells.
+    pub fn navigate_on_input(&mut self, ui: &Ui, response: &Response, canvas: Rect) -> bool {
+        if let Some(cursor) = response.hover_pos() {
+            let (scroll, pinch) = ui.input(|input| (input.scroll_delta.y, input.zoom_delta()));
+            let factor = (scroll * SCROLL_ZOOM_SPEED).exp() * pinch;
+            if factor != 1. {
+                self.zoom_at(canvas, cursor, factor);
+            }
+        }
+
+        let space_held = ui.input(|input| input.key_down(Key::Space));
+        let panning = response.dragged_by(PointerButton::Middle)
+            || (space_held && response.dragged_by(PointerButton::Primary));
+        if panning {
+            self.pan(response.drag_delta());
+        }
+
+        panning || space_held
+    }
+}
@@ -1,13 +1,20 @@
+use std::time::Duration;
+
 use super::GameView;
 use crate::constans::*;
 use crate::draw_utils;
+use crate::grid::{self, Colouring};
 use crate::open_view::OpenView;
-use eframe::{egui::Ui, epaint::Color32};
+use eframe::{
+    egui::{self, DragValue, Slider, Ui},
+    epaint::Color32,
+};
+use egui_file::FileDialog;
 
 pub fn draw_buttons(app: &mut GameView, ui: &mut Ui) -> Option<OpenView> {
     let mut to_return = None;
 
-    ui.horizontal(|ui| {
+    ui.horizontal_wrapped(|ui| {
         let pause_txt = if app.is_paused {
             BTN_RESUME_TXT
         } else {
@@ -17,14 +24,31 @@ pub fn draw_buttons(app: &mut GameView, ui: &mut Ui) -> Option<OpenView> {
             app.toogle_pause_resume();
         }
 
+        {
+            let can_go_back = app.history.current > app.history.first;
+            let previous_btn = draw_utils::button(BTN_PREVIOUS_TXT);
+            let previous_btn = ui.add_enabled(app.is_paused && can_go_back, previous_btn);
+            if previous_btn.clicked() {
+                app.step_back();
+            }
+        }
+
         {
             let next_btn = draw_utils::button(BTN_NEXT_TXT);
             let next_btn = ui.add_enabled(app.is_paused, next_btn);
             if next_btn.clicked() {
-                app.grid.tick();
+                app.step();
             }
         }
 
+        ui.label(draw_utils::create_rich_text(STEP_EXPONENT_TXT));
+        let mut exponent = app.step_exponent;
+        let exponent_field =
+            DragValue::new(&mut exponent).clamp_range(0..=app.grid.max_step_exponent());
+        if ui.add(exponent_field).changed() {
+            app.set_step_exponent(exponent);
+        }
+
         let reset_btn = draw_utils::button(BTN_RESET_TXT).fill(ERR_COLOR);
         if ui.add(reset_btn).clicked() {
             app.reset();
@@ -34,21 +58,247 @@ pub fn draw_buttons(app: &mut GameView, ui: &mut Ui) -> Option<OpenView> {
             app.pause();
             to_return = Some(OpenView::new(app.previous_view.clone()));
         };
+
+        if ui.add(draw_utils::button(BTN_FIT_TXT)).clicked() {
+            app.fit_requested = true;
+        }
+        ui.checkbox(
+            &mut app.follow_pattern,
+            draw_utils::create_rich_text(FOLLOW_TXT),
+        );
+
+        let mut parallel = app.grid.is_parallel();
+        if ui
+            .checkbox(&mut parallel, draw_utils::create_rich_text(PARALLEL_TXT))
+            .changed()
+        {
+            app.set_parallel(parallel);
+        }
+
+        if app.grid.detects_periods() {
+            let mut detect_periods = app.grid.is_detecting_periods();
+            let detect_checkbox = ui.checkbox(
+                &mut detect_periods,
+                draw_utils::create_rich_text(DETECT_PERIODS_TXT),
+            );
+            if detect_checkbox.changed() {
+                app.set_period_detection(detect_periods);
+            }
+        }
+        if app.grid.is_detecting_periods() {
+            ui.checkbox(
+                &mut app.pause_on_repetition,
+                draw_utils::create_rich_text(PAUSE_ON_REPETITION_TXT),
+            );
+        }
+
+        if app.grid.tracks_ages() {
+            draw_colouring(app, ui);
+        }
+
+        ui.checkbox(
+            &mut app.reset_to_edited,
+            draw_utils::create_rich_text(RESET_TO_EDITED_TXT),
+        );
+
+        if ui.add(draw_utils::button(BTN_SAVE_TXT)).clicked() {
+            let mut dialog = FileDialog::save_file(None);
+            dialog.open();
+            app.save_file_dialog = Some(dialog);
+        }
     });
 
+    draw_history(app, ui);
+    draw_image_export(app, ui);
+
+    if let Some(dialog) = &mut app.save_file_dialog {
+        if dialog.show(ui.ctx()).selected() {
+            if let Some(path) = dialog.path() {
+                app.save_to(&path);
+            }
+        }
+    }
+
+    match &app.last_save {
+        Some(Ok(path)) => draw_utils::computed_with_color(
+            ui,
+            format!("Saved to {}", path.to_string_lossy()),
+            Color32::GREEN,
+        ),
+        Some(Err(error)) => {
+            draw_utils::computed_with_color(ui, format!("Could not save: {}", error), ERR_COLOR)
+        }
+        None => (),
+    }
+
     ui.separator();
 
     to_return
 }
 
+/// Settings of exported images and the button which asks where to write them.
+fn draw_image_export(app: &mut GameView, ui: &mut Ui) {
+    ui.horizontal_wrapped(|ui| {
+        if ui.add(draw_utils::button(BTN_EXPORT_IMAGE_TXT)).clicked() {
+            let mut dialog = FileDialog::save_file(None);
+            dialog.open();
+            app.export_file_dialog = Some(dialog);
+        }
+        ui.label(draw_utils::create_rich_text(IMAGE_CELL_SIZE_TXT));
+        ui.add(DragValue::new(&mut app.image_cell_size).clamp_range(1..=MAX_IMAGE_CELL_SIZE));
+        ui.checkbox(
+            &mut app.image_grid_lines,
+            draw_utils::create_rich_text(IMAGE_GRID_LINES_TXT),
+        );
+    });
+
+    if let Some(dialog) = &mut app.export_file_dialog {
+        if dialog.show(ui.ctx()).selected() {
+            if let Some(path) = dialog.path() {
+                app.export_image_to(&path);
+            }
+        }
+    }
+
+    match &app.last_export {
+        Some(Ok(path)) => draw_utils::computed_with_color(
+            ui,
+            format!("Exported image to {}", path.to_string_lossy()),
+            Color32::GREEN,
+        ),
+        Some(Err(error)) => draw_utils::computed_with_color(
+            ui,
+            format!("Could not export image: {}", error),
+            ERR_COLOR,
+        ),
+        None => (),
+    }
+}
+
+/// Slider over the stored generations, moving it pauses and shows that generation.
+fn draw_history(app: &mut GameView, ui: &mut Ui) {
+    let range = app.history;
+    let mut position = range.current;
+    ui.horizontal(|ui| {
+        ui.label(draw_utils::create_rich_text(HISTORY_TXT));
+        let slider = Slider::new(&mut position, range.first..=range.last);
+        let slider = ui.add_enabled(range.first < range.last, slider);
+        if slider.changed() && position != range.current {
+            app.seek(position);
+        }
+    });
+}
+
+/// Lets the colouring and the colours of ages and trails be chosen.
+fn draw_colouring(app: &mut GameView, ui: &mut Ui) {
+    let mut drawing = app.grid.drawing().clone();
+    ui.label(draw_utils::create_rich_text(COLOURING_TXT));
+    egui::ComboBox::from_id_source(COLOURING_TXT)
+        .selected_text(drawing.colouring.to_string())
+        .show_ui(ui, |ui| {
+            for option in Colouring::ALL {
+                ui.selectable_value(&mut drawing.colouring, option, option.to_string());
+            }
+        });
+
+    let [youngest, oldest] = &mut drawing.age_gradient;
+    match drawing.colouring {
+        Colouring::States => (),
+        Colouring::Age => {
+            ui.color_edit_button_srgba(youngest)
+                .on_hover_text("Newborn cells");
+            ui.color_edit_button_srgba(oldest)
+                .on_hover_text("Oldest cells");
+        }
+        Colouring::Trails => {
+            ui.color_edit_button_srgba(&mut drawing.trail_color)
+                .on_hover_text("Cells which just died");
+        }
+    }
+
+    if drawing != *app.grid.drawing() {
+        app.set_drawing(drawing);
+    }
+}
+
 pub fn draw_stats(app: &GameView, ui: &mut Ui) {
     draw_utils::draw_grid(ui, "Game of life labels", |ui| {
         ui.label(draw_utils::create_rich_text("Passed ticks:"));
-        draw_utils::computed_value(ui, &app.grid.passed_tick().to_string());
+        let passed_ticks = match app.grid.edited_cells() {
+            0 => app.grid.passed_tick().to_string(),
+            edited => format!("{} ({} cells edited)", app.grid.passed_tick(), edited),
+        };
+        draw_utils::computed_value(ui, passed_ticks);
+        ui.end_row();
+
+        ui.label(draw_utils::create_rich_text("Alive cells:"));
+        draw_utils::computed_value(ui, app.grid.population().to_string());
+        ui.end_row();
+
+        ui.label(draw_utils::create_rich_text("Bounding box:"));
+        let bounds = app.grid.bounds();
+        let bounding_box = if bounds.height == 0 || bounds.width == 0 {
+            String::from("Empty")
+        } else {
+            format!(
+                "{} x {} from ({}, {}) to ({}, {})",
+                bounds.width,
+                bounds.height,
+                bounds.left,
+                bounds.top,
+                bounds.left + bounds.width as i64 - 1,
+                bounds.top + bounds.height as i64 - 1,
+            )
+        };
+        draw_utils::computed_value(ui, bounding_box);
+        ui.end_row();
+
+        if app.grid.is_detecting_periods() {
+            ui.label(draw_utils::create_rich_text("Pattern:"));
+            let pattern = app.grid.periodicity().map_or_else(
+                || String::from("Not repeating yet"),
+                |found| found.to_string(),
+            );
+            draw_utils::computed_value(ui, pattern);
+            ui.end_row();
+        }
+
+        ui.label(draw_utils::create_rich_text("Generations per step:"));
+        draw_utils::computed_value(ui, format!("2^{}", app.step_exponent));
         ui.end_row();
 
         ui.label(draw_utils::create_rich_text("Tick rate:"));
-        draw_utils::computed_value(ui, &format!("{} ms", app.tick_timer.interval_as_ms()));
+        let interval: Duration = app.previous_view.time_interval.into();
+        draw_utils::computed_value(ui, format!("{} ms", interval.as_millis()));
+        ui.end_row();
+
+        ui.label(draw_utils::create_rich_text("Rule:"));
+        draw_utils::computed_value(ui, app.grid.rule_name());
+        ui.end_row();
+
+        ui.label(draw_utils::create_rich_text("Engine:"));
+        draw_utils::computed_value(ui, app.grid.kind().to_string());
+        ui.end_row();
+
+        ui.label(draw_utils::create_rich_text("Stepping:"));
+        let stepping = if app.grid.is_parallel() {
+            format!("Parallel on {} threads", grid::available_threads())
+        } else {
+            String::from("Serial")
+        };
+        draw_utils::computed_value(ui, stepping);
+        ui.end_row();
+
+        ui.label(draw_utils::create_rich_text("Zoom:"));
+        draw_utils::computed_value(ui, format!("{:.0} %", app.camera.zoom() * 100.));
+        ui.end_row();
+
+        ui.label(draw_utils::create_rich_text("Boundary:"));
+        let boundary = match app.grid.boundary() {
+            Some(boundary) => boundary.to_string(),
+            None => String::from("Unbounded"),
+        };
+        draw_utils::computed_value(ui, boundary);
         ui.end_row();
 
         ui.label(draw_utils::create_rich_text("State:"));
@@ -0,0 +1,73 @@
+use eframe::egui::Response;
+
+use super::GameView;
+use crate::grid::{GridTransform, LifeCell};
+
+/// Toggles a cell on click, cells of hand written automata go through all states instead.
+/// Dragging paints with the state of the last click if started on a dead cell,
+/// otherwise it erases cells.
+pub fn edit_on_input(app: &mut GameView, response: &Response, transform: GridTransform) {
+    if response.drag_released() {
+        // Only strokes which painted cells are edits, releasing a pan is not.
+        if app.paint_stroke.take().is_some() {
+            app.remember_edited_state();
+        }
+        return;
+    }
+
+    let Some(pointer) = response.interact_pointer_pos() else {
+        return;
+    };
+    let Some(under) = app.grid.cell_under(transform, pointer) else {
+        return;
+    };
+
+    if response.clicked() {
+        let (y, x) = under;
+        let toggled = match (app.grid.automaton(), app.grid.cell_at(y, x)) {
+            (Some(automaton), cell) => {
+                LifeCell::from_state((cell.state() + 1) % automaton.states())
+            }
+            (None, LifeCell::Alive) => LifeCell::Dead,
+            (None, _) => LifeCell::Alive,
+        };
+        if toggled != LifeCell::Dead {
+            app.brush = toggled;
+        }
+        app.set_cell(y, x, toggled);
+        app.remember_edited_state();
+    } else if response.drag_started() {
+        let (y, x) = under;
+        let paint_with = match app.grid.cell_at(y, x) {
+            LifeCell::Dead => app.brush,
+            _ => LifeCell::Dead,
+        };
+        app.set_cell(y, x, paint_with);
+        app.paint_stroke = Some((paint_with, under));
+    } else if response.dragged() {
+        if let Some((paint_with, last)) = app.paint_stroke {
+            // Fast movements skip cells between two frames, so the gap is filled with a line.
+            for (y, x) in cells_on_line(last, under) {
+                app.set_cell(y, x, paint_with);
+            }
+            app.paint_stroke = Some((paint_with, under));
+        }
+    }
+}
+
+fn cells_on_line(from: (i64, i64), to: (i64, i64)) -> impl Iterator<Item = (i64, i64)> {
+    let (from_y, from_x) = (from.0 as f64, from.1 as f64);
+    let (to_y, to_x) = (to.0 as f64, to.1 as f64);
+    let steps = (to_y - from_y).abs().max((to_x - from_x).abs()) as i64;
+
+    (0..=steps).map(move |step| {
+        let progress = if steps == 0 {
+            0.
+        } else {
+            step as f64 / steps as f64
+        };
+        let y = from_y + (to_y - from_y) * progress;
+        let x = from_x + (to_x - from_x) * progress;
+        (y.round() as i64, x.round() as i64)
+    })
+}
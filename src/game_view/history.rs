@@ -0,0 +1,208 @@
+use std::collections::VecDeque;
+
+use crate::engine::Engine;
+
+/// Steps between two full copies of the engine.
+const KEYFRAME_INTERVAL: usize = 16;
+/// Older keyframes are dropped once all keyframes take more bytes than this.
+const MAX_HISTORY_BYTES: usize = 256 << 20;
+
+/// Stored generations which the ui can move between.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct HistoryRange {
+    pub first: usize,
+    pub last: usize,
+    pub current: usize,
+}
+
+/// Bounded history of the generations the worker went through.
+/// Only every few steps a compact copy of the engine is kept. Generations in between
+/// are calculated again from the copy before them with the same step sizes,
+/// which gives the same cells because every engine is deterministic.
+pub struct History {
+    keyframes: VecDeque<Keyframe>,
+    /// Position of the first generation of the first keyframe.
+    first: usize,
+    current: usize,
+    /// Bytes all keyframes take together.
+    bytes: usize,
+    max_bytes: usize,
+}
+
+struct Keyframe {
+    engine: Box<dyn Engine>,
+    bytes: usize,
+    /// Exponent of every step after the copy, each leading to the next stored generation.
+    steps: Vec<u32>,
+}
+
+impl Keyframe {
+    fn new(engine: &dyn Engine) -> Self {
+        Self {
+            engine: engine.keyframe(),
+            bytes: engine.keyframe_bytes(),
+            steps: Vec::new(),
+        }
+    }
+
+    /// Number of generations which can be restored from this keyframe.
+    fn len(&self) -> usize {
+        self.steps.len() + 1
+    }
+}
+
+impl History {
+    pub fn new(engine: &dyn Engine) -> Self {
+        let keyframe = Keyframe::new(engine);
+        Self {
+            bytes: keyframe.bytes,
+            keyframes: VecDeque::from([keyframe]),
+            first: 0,
+            current: 0,
+            max_bytes: MAX_HISTORY_BYTES,
+        }
+    }
+
+    pub fn range(&self) -> HistoryRange {
+        let stored: usize = self.keyframes.iter().map(Keyframe::len).sum();
+        HistoryRange {
+            first: self.first,
+            last: self.first + stored - 1,
+            current: self.current,
+        }
+    }
+
+    /// Remembers the generation the engine reached with a step after the current one.
+    /// Generations after the current one are forgotten, so stepping restarts from here.
+    pub fn record_step(&mut self, exponent: u32, engine: &dyn Engine) {
+        self.forget_future();
+        let last = self.keyframes.back_mut().expect("History has no keyframe");
+        if last.len() < KEYFRAME_INTERVAL {
+            last.steps.push(exponent);
+        } else {
+            self.push_keyframe(engine);
+        }
+        self.current += 1;
+    }
+
+    /// Remembers a generation changed by hand as if it followed the current one,
+    /// so the change can be undone by going back.
+    pub fn record_edit(&mut self, engine: &dyn Engine) {
+        self.forget_future();
+        self.push_keyframe(engine);
+        self.current += 1;
+    }
+
+    /// Calculates the generation at the given position again from the keyframe before it.
+    /// Returns none if the position is not stored.
+    pub fn seek(&mut self, position: usize) -> Option<Box<dyn Engine>> {
+        let (keyframe, offset) = self.locate(position)?;
+        let keyframe = &self.keyframes[keyframe];
+        let mut engine = keyframe.engine.snapshot();
+        engine.restore_keyframe();
+        for &exponent in &keyframe.steps[..offset] {
+            engine.step_pow2(exponent);
+        }
+        self.current = position;
+        Some(engine)
+    }
+
+    /// Index of the keyframe and the number of steps after it which lead to the position.
+    fn locate(&self, position: usize) -> Option<(usize, usize)> {
+        let mut offset = position.checked_sub(self.first)?;
+        for (index, keyframe) in self.keyframes.iter().enumerate() {
+            if offset < keyframe.len() {
+                return Some((index, offset));
+            }
+            offset -= keyframe.len();
+        }
+        None
+    }
+
+    fn forget_future(&mut self) {
+        let (keyframe, offset) = self
+            .locate(self.current)
+            .expect("Current generation is always stored");
+        for forgotten in self.keyframes.drain(keyframe + 1..) {
+            self.bytes -= forgotten.bytes;
+        }
+        self.keyframes[keyframe].steps.truncate(offset);
+    }
+
+    /// The newest keyframe is always kept, even if it alone is larger than the budget.
+    fn push_keyframe(&mut self, engine: &dyn Engine) {
+        let keyframe = Keyframe::new(engine);
+        self.bytes += keyframe.bytes;
+        self.keyframes.push_back(keyframe);
+        while self.bytes > self.max_bytes && self.keyframes.len() > 1 {
+            let dropped = self.keyframes.pop_front().expect("History has no keyframe");
+            self.bytes -= dropped.bytes;
+            self.first += dropped.len();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::{rle, Boundary, Grid, GridDrawSettings, LifeCell};
+
+    /// Generations rule, so that dying stages have to be restored as well.
+    fn brians_brain() -> Grid {
+        let text = "x = 40, y = 40, rule = /2/3\n5$10b3o$10bobo$11bo!\n";
+        let text = rle::parse(text).expect("Pattern is valid");
+        let rule = text.rule().expect("Header has a rule");
+        Grid::new(text, rule, Boundary::Torus, GridDrawSettings::default())
+    }
+
+    fn cells(engine: &dyn Engine) -> Vec<LifeCell> {
+        let bounds = engine.bounds();
+        let rows = bounds.top..bounds.top + bounds.height as i64;
+        let columns = bounds.left..bounds.left + bounds.width as i64;
+        rows.flat_map(|y| columns.clone().map(move |x| engine.cell_at(y, x)))
+            .collect()
+    }
+
+    #[test]
+    fn restores_the_same_generations_as_stepping() {
+        let mut grid = brians_brain();
+        let mut history = History::new(&grid);
+        let mut generations = vec![cells(&grid)];
+        for _ in 0..50 {
+            grid.tick();
+            history.record_step(0, &grid);
+            generations.push(cells(&grid));
+        }
+        assert!(generations[50]
+            .iter()
+            .any(|&cell| matches!(cell, LifeCell::Dying(_))));
+
+        for position in [0, 7, 16, 17, 33, 50] {
+            let mut restored = history.seek(position).expect("Generation is stored");
+            assert_eq!(cells(restored.as_ref()), generations[position]);
+            // Restored keyframes can step again.
+            if position < 50 {
+                restored.tick();
+                assert_eq!(cells(restored.as_ref()), generations[position + 1]);
+            }
+        }
+    }
+
+    #[test]
+    fn drops_the_oldest_keyframes_beyond_the_budget() {
+        let mut grid = brians_brain();
+        let mut history = History::new(&grid);
+        history.max_bytes = 3 * grid.keyframe_bytes();
+        for _ in 0..100 {
+            grid.tick();
+            history.record_step(0, &grid);
+        }
+
+        assert!(history.bytes <= history.max_bytes);
+        assert_eq!(history.keyframes.len(), 3);
+        let range = history.range();
+        assert_eq!(range.last, 100);
+        assert!(history.seek(range.first - 1).is_none());
+        assert!(history.seek(range.first).is_some());
+    }
+}
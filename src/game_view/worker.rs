@@ -0,0 +1,178 @@
+use std::{
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender, TrySendError},
+    thread,
+    time::Duration,
+};
+
+use super::history::{History, HistoryRange};
+use crate::{
+    engine::Engine,
+    grid::{GridDrawSettings, LifeCell},
+    timer::Timer,
+};
+
+/// How long the worker waits before it offers a snapshot again
+/// which the ui has not picked up yet.
+const PUBLISH_RETRY: Duration = Duration::from_millis(16);
+
+pub enum Command {
+    Resume,
+    Pause,
+    Step,
+    Replace(Box<dyn Engine>),
+    SetCell(i64, i64, LifeCell),
+    SetParallel(bool),
+    SetPeriodDetection(bool),
+    SetDrawing(GridDrawSettings),
+    /// Every step advances by 2 to the power of this exponent generations.
+    SetStepExponent(u32),
+    /// Goes back to the generation before the current one in the history.
+    Previous,
+    /// Goes to the generation at the given position in the history.
+    Seek(usize),
+    /// Remembers the cells changed since the last step as a new generation in the history.
+    RecordEdit,
+}
+
+/// Copy of the engine together with the generations the history holds.
+pub struct Snapshot {
+    pub engine: Box<dyn Engine>,
+    pub history: HistoryRange,
+}
+
+/// Owns the engine on a background thread and calculates generations there,
+/// so a slow generation does not freeze the window.
+/// The ui sends commands and receives copies of the engine after changes.
+pub struct Worker {
+    commands: Sender<Command>,
+    snapshots: Receiver<Snapshot>,
+}
+
+impl Worker {
+    pub fn spawn(grid: Box<dyn Engine>, interval: Duration) -> Self {
+        let (commands, received_commands) = mpsc::channel();
+        // Only one snapshot waits at a time. Newer generations replace it once the ui took it.
+        let (published_snapshots, snapshots) = mpsc::sync_channel(1);
+
+        thread::Builder::new()
+            .name(String::from("Simulation"))
+            .spawn(move || {
+                run(
+                    grid,
+                    Timer::new(interval),
+                    received_commands,
+                    published_snapshots,
+                )
+            })
+            .expect("Could not start thread for the simulation");
+
+        Self {
+            commands,
+            snapshots,
+        }
+    }
+
+    pub fn send(&self, command: Command) {
+        // The worker only stops if this side is dropped, so sending can not fail.
+        let _ = self.commands.send(command);
+    }
+
+    /// Returns the most recent snapshot published since the last call.
+    pub fn latest_snapshot(&self) -> Option<Snapshot> {
+        self.snapshots.try_iter().last()
+    }
+}
+
+fn run(
+    mut grid: Box<dyn Engine>,
+    mut timer: Timer,
+    commands: Receiver<Command>,
+    snapshots: SyncSender<Snapshot>,
+) {
+    let mut unpublished = false;
+    let mut step_exponent = 0;
+    let mut history = History::new(grid.as_ref());
+
+    loop {
+        let received = if !timer.is_paused() {
+            commands.recv_timeout(timer.remaining())
+        } else if unpublished {
+            commands.recv_timeout(PUBLISH_RETRY)
+        } else {
+            commands.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        };
+
+        match received {
+            Ok(Command::Resume) => timer.resume(),
+            Ok(Command::Pause) => timer.pause(),
+            Ok(Command::Step) => {
+                grid.step_pow2(step_exponent);
+                history.record_step(step_exponent, grid.as_ref());
+                unpublished = true;
+            }
+            Ok(Command::Replace(new_grid)) => {
+                timer.reset();
+                grid = new_grid;
+                history = History::new(grid.as_ref());
+                unpublished = true;
+            }
+            Ok(Command::SetCell(y, x, cell)) => {
+                grid.set_cell_at(y, x, cell);
+                unpublished = true;
+            }
+            Ok(Command::SetParallel(parallel)) => grid.set_parallel(parallel),
+            Ok(Command::SetPeriodDetection(enabled)) => {
+                grid.set_period_detection(enabled);
+                unpublished = true;
+            }
+            Ok(Command::SetDrawing(drawing)) => grid.set_drawing(drawing),
+            Ok(Command::SetStepExponent(exponent)) => step_exponent = exponent,
+            Ok(Command::Previous) => {
+                let previous = history.range().current.checked_sub(1);
+                if let Some(restored) = previous.and_then(|position| history.seek(position)) {
+                    grid = restore(restored, grid.as_ref());
+                    unpublished = true;
+                }
+            }
+            Ok(Command::Seek(position)) => {
+                if let Some(restored) = history.seek(position) {
+                    grid = restore(restored, grid.as_ref());
+                    unpublished = true;
+                }
+            }
+            Ok(Command::RecordEdit) => {
+                history.record_edit(grid.as_ref());
+                unpublished = true;
+            }
+            Err(RecvTimeoutError::Timeout) => (),
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+
+        if timer.is_up() {
+            timer.reset_time();
+            grid.step_pow2(step_exponent);
+            history.record_step(step_exponent, grid.as_ref());
+            unpublished = true;
+        }
+
+        if unpublished {
+            let snapshot = Snapshot {
+                engine: grid.snapshot(),
+                history: history.range(),
+            };
+            match snapshots.try_send(snapshot) {
+                Ok(()) => unpublished = false,
+                Err(TrySendError::Full(_)) => (),
+                Err(TrySendError::Disconnected(_)) => return,
+            }
+        }
+    }
+}
+
+/// Keeps the settings of the current engine for a generation restored from the history.
+fn restore(mut restored: Box<dyn Engine>, current: &dyn Engine) -> Box<dyn Engine> {
+    restored.set_parallel(current.is_parallel());
+    restored.set_period_detection(current.is_detecting_periods());
+    restored.set_drawing(current.drawing().clone());
+    restored
+}
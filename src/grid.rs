@@ -1,160 +1,769 @@
-use std::iter::repeat;
+use std::{iter::repeat_n, sync::Arc};
 
-use eframe::{
-    egui::Ui,
-    epaint::{Color32, Pos2, Rect, RectShape, Rounding, Shape, Stroke},
-};
+use eframe::{egui::Ui, epaint::Color32};
 
+use crate::engine::{Engine, EngineKind};
+
+mod automaton;
+mod bit_board;
+mod boundary;
+mod cell_ages;
 mod dead_alive_char_cells;
+mod dying_cells;
 mod outer;
+mod periodicity;
+pub mod renderer;
+mod rule;
+mod state_char_cells;
+mod summed_area;
 mod text_data;
+use automaton::AutomatonCells;
+pub use automaton::{built_in, Automaton};
+pub use bit_board::next_word_of;
+use bit_board::BitBoard;
+pub use boundary::{Boundary, InvalidBoundary};
+use cell_ages::CellAges;
+use dying_cells::DyingCells;
 pub use outer::*;
-pub use text_data::TextData;
+pub use periodicity::Periodicity;
+use periodicity::{PatternKey, PeriodDetector};
+pub use rule::{CountConditions, InvalidRule, LargerThanLife, NeighbourRows, Rule};
+pub use state_char_cells::StateCharCells;
+use summed_area::SummedArea;
+pub use text_data::{read_file, rle, PatternFormat, TextData, MAX_PATTERN_CELLS};
 pub mod text_load_error;
 pub use dead_alive_char_cells::{DeadAliveCharCell, InvalidCharCell};
 
-const STROKE_WIDTH: f32 = 1.;
+/// A grid calculates every single generation, so larger steps take too long.
+const MAX_STEP_EXPONENT: u32 = 10;
+/// Rows and columns of the neighbours relative to a cell.
+/// Also the order of the bits in the neighbourhood of a cell.
+pub const NEIGHBOUR_OFFSETS: [(isize, isize); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+/// Rows and columns of the six neighbours on a hexagonal grid for even and for odd rows.
+/// Odd rows are shifted half a cell to the right, so their neighbours above and below are too.
+pub const HEXAGONAL_NEIGHBOUR_OFFSETS: [[(isize, isize); 6]; 2] = [
+    [(-1, -1), (-1, 0), (0, -1), (0, 1), (1, -1), (1, 0)],
+    [(-1, 0), (-1, 1), (0, -1), (0, 1), (1, 0), (1, 1)],
+];
 
 #[derive(Clone)]
 pub struct Grid {
-    all_cells: Vec<LifeCell>,
+    cells: BitBoard,
+    next_cells: BitBoard,
+    /// Only used by generations rules.
+    dying: Option<DyingCells>,
+    /// Not tracked for hand written automata.
+    ages: Option<CellAges>,
+    /// Replaces the cells above and the rule for hand written automata.
+    automaton: Option<AutomatonCells>,
+    periods: PeriodDetector,
+    /// Only look for repetitions if someone wants to know about them.
+    detect_periods: bool,
     drawing: GridDrawSettings,
+    rule: Rule,
+    boundary: Boundary,
     height: usize,
     width: usize,
-    passed_ticks: usize,
+    passed_ticks: u64,
+    edited_cells: usize,
+    parallel: bool,
 }
 
 impl Grid {
-    pub fn new(text: TextData, drawing: GridDrawSettings) -> Self {
+    pub fn new(text: TextData, rule: Rule, boundary: Boundary, drawing: GridDrawSettings) -> Self {
         let (height, width) = (text.height(), text.width());
-        let mut all_cells = Vec::with_capacity(height * width);
+        let mut cells = BitBoard::new(height, width);
 
-        let initial_cells = all_coords(height, width).map(|(y, x)| text.cell_at_y_x(y, x));
-        all_cells.extend(initial_cells);
+        for (y, x) in all_coords(height, width) {
+            cells.set(y, x, text.cell_at_y_x(y, x) == LifeCell::Alive);
+        }
 
+        // States after alive are the dying stages of generations rules.
+        let dying = rule.is_generations().then(|| {
+            let mut dying = DyingCells::new(height, width);
+            for (y, x) in all_coords(height, width) {
+                let state = text.cell_at_y_x(y, x).state();
+                if (2..rule.states()).contains(&state) {
+                    dying.set_stage(y, x, state - 1);
+                }
+            }
+            dying
+        });
+
+        let ages = CellAges::new(&cells, height, width);
         Self {
-            all_cells,
+            next_cells: cells.clone(),
+            cells,
+            dying,
+            ages: Some(ages),
+            automaton: None,
+            periods: PeriodDetector::default(),
+            detect_periods: false,
             height,
             width,
             drawing,
+            rule,
+            boundary,
             passed_ticks: 0,
+            edited_cells: 0,
+            parallel: false,
         }
     }
 
-    pub fn draw_at(&self, ui: &mut Ui, start: Pos2) {
-        let cell_size = self.drawing.cell_size as f32;
-        let (height, width) = (self.height, self.width);
-        let mut output = Vec::with_capacity(height * width);
-
+    /// Grid whose cells are calculated by a hand written automaton instead of a life like rule.
+    pub fn with_automaton(
+        text: TextData,
+        automaton: Arc<dyn Automaton>,
+        boundary: Boundary,
+        drawing: GridDrawSettings,
+    ) -> Self {
+        let (height, width) = (text.height(), text.width());
+        let mut cells = AutomatonCells::new(automaton, height, width);
         for (y, x) in all_coords(height, width) {
-            let current_cell = *self.all_cells.get(y_x_to_index(width, y, x)).unwrap();
-            let y = y as f32;
-            let x = x as f32;
-            let min_y = (y * cell_size) + start.y;
-            let min_x = (x * cell_size) + start.x;
-            let max_x = min_x + cell_size;
-            let max_y = min_y + cell_size;
-
-            let color = self.get_color_for_cell(current_cell);
-            let shape = RectShape {
-                rect: Rect {
-                    min: Pos2 { x: min_x, y: min_y },
-                    max: Pos2 { x: max_x, y: max_y },
-                },
-                rounding: Rounding::default(),
-                fill: color,
-                stroke: Stroke {
-                    width: STROKE_WIDTH,
-                    color: self.drawing.stroke_color,
-                },
-            };
-
-            output.push(Shape::Rect(shape));
-        }
-
-        ui.painter().extend(output);
+            cells.set(y, x, text.cell_at_y_x(y, x).state());
+        }
+
+        let empty = BitBoard::new(0, 0);
+        Self {
+            cells: empty.clone(),
+            next_cells: empty,
+            dying: None,
+            ages: None,
+            automaton: Some(cells),
+            periods: PeriodDetector::default(),
+            detect_periods: false,
+            drawing,
+            rule: Rule::default(),
+            boundary,
+            height,
+            width,
+            passed_ticks: 0,
+            edited_cells: 0,
+            parallel: false,
+        }
     }
 
+    /// Changes a single cell by hand. Counts as an edit if the cell changed.
+    pub fn set_cell(&mut self, y: usize, x: usize, cell: LifeCell) {
+        if self.cell_at_y_x(y, x) == cell {
+            return;
+        }
+        if let Some(automaton) = &mut self.automaton {
+            automaton.set(y, x, cell.state());
+        } else {
+            self.cells.set(y, x, cell == LifeCell::Alive);
+            if let Some(dying) = &mut self.dying {
+                let stage = match cell {
+                    LifeCell::Dying(stage) => stage,
+                    _ => 0,
+                };
+                dying.set_stage(y, x, stage);
+            }
+            if let Some(ages) = &mut self.ages {
+                ages.set(y, x, cell == LifeCell::Alive);
+            }
+        }
+        self.periods.clear();
+        self.edited_cells += 1;
+    }
+
+    /// Calculates the next generation into the second buffer and swaps both buffers.
+    /// While periods are detected, every generation is remembered.
     pub fn tick(&mut self) {
-        let to_apply = self.calcalute_change();
+        if self.detect_periods && self.periods.is_empty() {
+            self.record_generation();
+        }
+        self.passed_ticks += 1;
+        let threads = if self.parallel {
+            available_threads()
+        } else {
+            1
+        };
+        match &mut self.automaton {
+            Some(automaton) => automaton.step(self.boundary, threads),
+            None => self.step_life(threads),
+        }
+        if self.detect_periods {
+            self.record_generation();
+        }
+    }
 
-        self.all_cells
-            .iter_mut()
-            .zip(to_apply.into_iter())
-            .for_each(|(old_cell, new_cell)| *old_cell = new_cell);
+    /// Calculates the next generation of the life like rule.
+    fn step_life(&mut self, threads: usize) {
+        if let Some(larger_than_life) = self.rule.larger_than_life() {
+            self.step_larger_than_life(larger_than_life, threads);
+        } else if self.rule.is_hexagonal() || !self.rule.is_totalistic() {
+            // Arrangements of neighbours and hexagonal neighbours
+            // can not be counted by the word parallel step.
+            let Self {
+                cells,
+                next_cells,
+                rule,
+                boundary,
+                height,
+                width,
+                ..
+            } = self;
+            next_cells.fill_with(threads, |y, x| {
+                let cell = if cells.get(y, x) {
+                    LifeCell::Alive
+                } else {
+                    LifeCell::Dead
+                };
+                let offsets = neighbour_offsets(rule, y);
+                let neighbourhood =
+                    neighbourhood(cells, *boundary, (*height, *width), offsets, y, x);
+                rule.next_cell(cell, neighbourhood) == LifeCell::Alive
+            });
+        } else {
+            let wrap = self.boundary == Boundary::Torus;
+            if self.parallel {
+                self.cells
+                    .step_into_parallel(&mut self.next_cells, &self.rule, wrap, threads);
+            } else {
+                self.cells.step_into(&mut self.next_cells, &self.rule, wrap);
+            }
+            if self.boundary.is_twisted() {
+                self.fix_twisted_border();
+            }
+        }
+        if let Some(dying) = &mut self.dying {
+            dying.advance(&self.cells, &mut self.next_cells, self.rule.states());
+        }
+        if let Some(ages) = &mut self.ages {
+            ages.advance(&self.cells, &self.next_cells);
+        }
+        std::mem::swap(&mut self.cells, &mut self.next_cells);
+    }
 
-        self.passed_ticks += 1;
+    pub fn cell_at_y_x(&self, y: usize, x: usize) -> LifeCell {
+        if let Some(automaton) = &self.automaton {
+            return LifeCell::from_state(automaton.get(y, x));
+        }
+        if self.cells.get(y, x) {
+            return LifeCell::Alive;
+        }
+        match self.dying.as_ref().map(|dying| dying.stage(y, x)) {
+            Some(0) | None => LifeCell::Dead,
+            Some(stage) => LifeCell::Dying(stage),
+        }
     }
 
-    pub fn passed_tick(&self) -> usize {
-        self.passed_ticks
+    /// Cells of the bounding box of all cells which are not dead packed into words.
+    /// The size of the box comes first, so that differently shaped boxes never look the same.
+    fn pattern_key(&self) -> PatternKey {
+        let area = match (&self.automaton, &self.dying) {
+            (Some(automaton), _) => automaton.bounding_box(),
+            (None, dying) => {
+                let alive = self.cells.bounding_box();
+                let dying = dying.as_ref().and_then(|dying| dying.mask().bounding_box());
+                match (alive, dying) {
+                    (Some((rows, columns)), Some((dying_rows, dying_columns))) => Some((
+                        rows.start.min(dying_rows.start)..rows.end.max(dying_rows.end),
+                        columns.start.min(dying_columns.start)..columns.end.max(dying_columns.end),
+                    )),
+                    (alive, dying) => alive.or(dying),
+                }
+            }
+        };
+        let Some((rows, columns)) = area else {
+            return PatternKey::default();
+        };
+
+        let mut cells = vec![rows.len() as u64, columns.len() as u64];
+        if let Some(automaton) = &self.automaton {
+            automaton.append_area(rows.clone(), columns.clone(), &mut cells);
+        } else {
+            self.cells
+                .append_area(rows.clone(), columns.clone(), &mut cells);
+            if let Some(dying) = &self.dying {
+                dying
+                    .mask()
+                    .append_area(rows.clone(), columns.clone(), &mut cells);
+                dying.append_stages(rows.clone(), columns.clone(), &mut cells);
+            }
+        }
+        PatternKey {
+            corner: (rows.start as i64, columns.start as i64),
+            cells,
+        }
     }
 
-    fn count_alive_cells(&self, y: usize, x: usize) -> usize {
+    /// Remembers the current generation to find repetitions of the whole pattern,
+    /// also after it moved.
+    fn record_generation(&mut self) {
+        let key = self.pattern_key();
+        self.periods.record(self.passed_ticks, key);
+    }
+
+    /// Counts the neighbours in the radius of every cell with a summed area table,
+    /// so that a larger radius barely takes longer.
+    fn step_larger_than_life(&mut self, larger_than_life: LargerThanLife, threads: usize) {
+        let size = (self.height, self.width);
+        let radius = larger_than_life.radius() as usize;
+        let sums = SummedArea::new(&self.cells, self.boundary, size, radius);
+        let neighbour_rows = larger_than_life.neighbour_rows();
+
+        let cells = &self.cells;
+        self.next_cells.fill_with(threads, |y, x| {
+            let alive = cells.get(y, x);
+            let mut neighbours = sums.count(y, x, &neighbour_rows);
+            if alive && !larger_than_life.includes_middle() {
+                neighbours -= 1;
+            }
+            if alive {
+                larger_than_life.is_survival(neighbours)
+            } else {
+                larger_than_life.is_birth(neighbours)
+            }
+        });
+    }
+
+    /// The word parallel step only knows planes and tori.
+    /// Cells at the edges of other topologies are calculated again one by one.
+    fn fix_twisted_border(&mut self) {
         let (height, width) = (self.height, self.width);
-        let left_x = (x + (width - 1)) % width;
-        let right_x = (x + 1) % width;
-        let top_y = (y + (height - 1)) % height;
-        let bottom_y = (y + 1) % height;
-
-        let mut found_alive_adjacant = 0;
-        for (y, x) in [
-            (top_y, left_x),
-            (top_y, x),
-            (top_y, right_x),
-            (y, left_x),
-            (y, right_x),
-            (bottom_y, left_x),
-            (bottom_y, x),
-            (bottom_y, right_x),
-        ] {
-            let index = y_x_to_index(width, y, x);
-            let current_cell = self.all_cells.get(index).unwrap_or_else(|| {
-                panic!(
-                    "Out of bounds index with x and y: ({},{}), width and height ({}, {}), index {} and length {}",
-                    x, y, width, height, index, self.all_cells.len()
+        let border = (0..height)
+            .flat_map(|y| [(y, 0), (y, width.saturating_sub(1))])
+            .chain((0..width).flat_map(|x| [(0, x), (height.saturating_sub(1), x)]));
+
+        let fixed: Vec<(usize, usize, LifeCell)> = border
+            .map(|(y, x)| {
+                let neighbourhood = neighbourhood(
+                    &self.cells,
+                    self.boundary,
+                    (height, width),
+                    &NEIGHBOUR_OFFSETS,
+                    y,
+                    x,
+                );
+                (
+                    y,
+                    x,
+                    self.rule.next_cell(self.cell_at_y_x(y, x), neighbourhood),
                 )
-            });
-            match current_cell {
-                LifeCell::Alive => found_alive_adjacant += 1,
-                LifeCell::Dead => (),
-            };
+            })
+            .collect();
+
+        for (y, x, cell) in fixed {
+            self.next_cells.set(y, x, cell == LifeCell::Alive);
+        }
+    }
+}
+
+impl Engine for Grid {
+    fn kind(&self) -> EngineKind {
+        EngineKind::Grid
+    }
+
+    /// The ui only shows the found repetition, so the remembered generations are left out.
+    fn snapshot(&self) -> Box<dyn Engine> {
+        Box::new(Self {
+            cells: self.cells.clone(),
+            next_cells: self.next_cells.clone(),
+            dying: self.dying.clone(),
+            ages: self.ages.clone(),
+            automaton: self.automaton.clone(),
+            periods: self.periods.without_history(),
+            detect_periods: self.detect_periods,
+            drawing: self.drawing.clone(),
+            rule: self.rule,
+            boundary: self.boundary,
+            height: self.height,
+            width: self.width,
+            passed_ticks: self.passed_ticks,
+            edited_cells: self.edited_cells,
+            parallel: self.parallel,
+        })
+    }
+
+    /// Keeps the cells and dying stages. Ages start over once the keyframe is restored.
+    fn keyframe(&self) -> Box<dyn Engine> {
+        Box::new(Self {
+            cells: self.cells.clone(),
+            next_cells: BitBoard::new(0, 0),
+            dying: self.dying.clone(),
+            ages: None,
+            automaton: self.automaton.as_ref().map(AutomatonCells::without_buffer),
+            periods: PeriodDetector::default(),
+            detect_periods: self.detect_periods,
+            drawing: self.drawing.clone(),
+            rule: self.rule,
+            boundary: self.boundary,
+            height: self.height,
+            width: self.width,
+            passed_ticks: self.passed_ticks,
+            edited_cells: self.edited_cells,
+            parallel: self.parallel,
+        })
+    }
+
+    fn restore_keyframe(&mut self) {
+        match &mut self.automaton {
+            Some(automaton) => automaton.restore_buffer(),
+            None => {
+                self.next_cells = BitBoard::new(self.height, self.width);
+                self.ages = Some(CellAges::new(&self.cells, self.height, self.width));
+            }
+        }
+    }
+
+    fn keyframe_bytes(&self) -> usize {
+        let dying = self.dying.as_ref().map_or(0, DyingCells::stored_bytes);
+        let automaton = self
+            .automaton
+            .as_ref()
+            .map_or(0, AutomatonCells::stored_bytes);
+        self.cells.stored_bytes() + dying + automaton
+    }
+
+    fn tick(&mut self) {
+        Grid::tick(self);
+    }
+
+    fn max_step_exponent(&self) -> u32 {
+        MAX_STEP_EXPONENT
+    }
+
+    fn passed_tick(&self) -> u64 {
+        self.passed_ticks
+    }
+
+    fn population(&self) -> u64 {
+        match &self.automaton {
+            Some(automaton) => automaton.count_non_empty(),
+            None => self.cells.count_alive(),
+        }
+    }
+
+    fn cell_at(&self, y: i64, x: i64) -> LifeCell {
+        if self.bounds().contains(y, x) {
+            self.cell_at_y_x(y as usize, x as usize)
+        } else {
+            LifeCell::Dead
+        }
+    }
+
+    fn set_cell_at(&mut self, y: i64, x: i64, cell: LifeCell) {
+        if self.bounds().contains(y, x) {
+            self.set_cell(y as usize, x as usize, cell);
+        }
+    }
+
+    fn edited_cells(&self) -> usize {
+        self.edited_cells
+    }
+
+    fn bounds(&self) -> CellBounds {
+        CellBounds {
+            top: 0,
+            left: 0,
+            height: self.height as u64,
+            width: self.width as u64,
         }
+    }
 
-        found_alive_adjacant
+    fn draw_at(&self, ui: &mut Ui, transform: GridTransform) {
+        Grid::draw_at(self, ui, transform);
     }
 
-    fn calcalute_change(&self) -> Vec<LifeCell> {
-        let mut to_return = Vec::with_capacity(self.all_cells.len());
-        for (y, x) in all_coords(self.height, self.width) {
-            let cell_count = self.count_alive_cells(y, x);
-            let index = y_x_to_index(self.width, y, x);
-            let current_cell = self.all_cells.get(index).unwrap();
-            let new_cell_val = match (*current_cell, cell_count) {
-                (LifeCell::Alive, alive_count) if alive_count < 2 => LifeCell::Dead,
-                (LifeCell::Alive, alive_count) if (2..=3).contains(&alive_count) => LifeCell::Alive,
-                (LifeCell::Alive, alive_count) if alive_count > 3 => LifeCell::Dead,
-                (LifeCell::Dead, 3) => LifeCell::Alive,
-                (otherwise, _) => otherwise,
-            };
+    fn drawing(&self) -> &GridDrawSettings {
+        &self.drawing
+    }
 
-            to_return.push(new_cell_val);
+    fn set_drawing(&mut self, drawing: GridDrawSettings) {
+        self.drawing = drawing;
+    }
+
+    fn cell_color(&self, y: i64, x: i64) -> Color32 {
+        if self.bounds().contains(y, x) {
+            self.color_at(y as usize, x as usize)
+        } else {
+            self.drawing.color_for(LifeCell::Dead)
         }
-        to_return
     }
 
-    fn get_color_for_cell(&self, cell: LifeCell) -> Color32 {
-        match cell {
-            LifeCell::Alive => self.drawing.dead_cell_color,
-            LifeCell::Dead => self.drawing.alive_cell_color,
+    fn tracks_ages(&self) -> bool {
+        self.ages.is_some()
+    }
+
+    fn detects_periods(&self) -> bool {
+        true
+    }
+
+    fn set_period_detection(&mut self, enabled: bool) {
+        if enabled != self.detect_periods {
+            self.periods.clear();
         }
+        self.detect_periods = enabled;
+    }
+
+    fn is_detecting_periods(&self) -> bool {
+        self.detect_periods
+    }
+
+    fn periodicity(&self) -> Option<Periodicity> {
+        self.periods.found()
+    }
+
+    fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    fn boundary(&self) -> Option<Boundary> {
+        Some(self.boundary)
+    }
+
+    fn automaton(&self) -> Option<&dyn Automaton> {
+        self.automaton.as_ref().map(AutomatonCells::automaton)
+    }
+
+    fn set_parallel(&mut self, parallel: bool) {
+        self.parallel = parallel;
+    }
+
+    fn is_parallel(&self) -> bool {
+        self.parallel
     }
 }
 
+/// Returns the alive neighbours of a cell as bits in the order of `NEIGHBOUR_OFFSETS`.
+fn neighbourhood(
+    cells: &BitBoard,
+    boundary: Boundary,
+    (height, width): (usize, usize),
+    offsets: &[(isize, isize)],
+    y: usize,
+    x: usize,
+) -> u8 {
+    offsets
+        .iter()
+        .enumerate()
+        .filter(|(_, (offset_y, offset_x))| {
+            boundary
+                .resolve(height, width, y as isize + offset_y, x as isize + offset_x)
+                .is_some_and(|(y, x)| cells.get(y, x))
+        })
+        .fold(0, |neighbourhood, (bit, _)| neighbourhood | 1 << bit)
+}
+
+/// Neighbours of a cell in the given row, which only differ between rows on hexagonal grids.
+fn neighbour_offsets(rule: &Rule, y: usize) -> &'static [(isize, isize)] {
+    if rule.is_hexagonal() {
+        &HEXAGONAL_NEIGHBOUR_OFFSETS[y % 2]
+    } else {
+        &NEIGHBOUR_OFFSETS
+    }
+}
+
+pub fn available_threads() -> usize {
+    std::thread::available_parallelism()
+        .map(|threads| threads.get())
+        .unwrap_or(1)
+}
+
 pub fn y_x_to_index(width: usize, y: usize, x: usize) -> usize {
     (y * width) + x
 }
 
 pub fn all_coords(height: usize, width: usize) -> impl Iterator<Item = (usize, usize)> {
-    (0..height).flat_map(move |y| repeat(y).take(width).zip(0..width))
+    (0..height).flat_map(move |y| repeat_n(y, width).zip(0..width))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Grid of the RLE pattern with the given size, run on a plane.
+    fn grid(size: (usize, usize), cells: &str, rule: &str) -> Grid {
+        let text = format!(
+            "x = {}, y = {}, rule = {}\n{}\n",
+            size.1, size.0, rule, cells
+        );
+        let text = rle::parse(&text).expect("Pattern is valid");
+        let rule = rule.parse().expect("Rule is valid");
+        Grid::new(text, rule, Boundary::Plane, GridDrawSettings::default())
+    }
+
+    fn first_repetition(mut grid: Grid, generations: usize) -> Option<Periodicity> {
+        grid.set_period_detection(true);
+        for _ in 0..generations {
+            grid.tick();
+            if let Some(found) = grid.periodicity() {
+                return Some(found);
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn finds_still_lifes_oscillators_and_spaceships() {
+        let block = grid((6, 6), "2$2b2o$2b2o!", "B3/S23");
+        assert_eq!(first_repetition(block, 4), Some(Periodicity::Stable));
+
+        let blinker = grid((5, 5), "2$b3o!", "B3/S23");
+        let oscillator = Periodicity::Oscillator { period: 2 };
+        assert_eq!(first_repetition(blinker, 4), Some(oscillator));
+
+        let glider = grid((20, 20), "bo$2bo$3o!", "B3/S23");
+        let spaceship = Periodicity::Spaceship {
+            period: 4,
+            dx: 1,
+            dy: 1,
+        };
+        assert_eq!(first_repetition(glider, 8), Some(spaceship));
+
+        // Lightweight spaceship, far wider than a word so that rows are shifted across words.
+        let lwss = grid((12, 150), "2$70bo2bo$74bo$70bo3bo$71b4o!", "B3/S23");
+        let spaceship = Periodicity::Spaceship {
+            period: 4,
+            dx: 2,
+            dy: 0,
+        };
+        assert_eq!(first_repetition(lwss, 8), Some(spaceship));
+    }
+
+    #[test]
+    fn dying_cells_count_as_part_of_the_pattern() {
+        // A single cell of Brian's Brain dies and fades, which takes two generations.
+        let single = grid((3, 3), "$bo!", "/2/3");
+        let mut grid = single.clone();
+        grid.set_period_detection(true);
+        grid.tick();
+        assert_eq!(grid.periodicity(), None);
+        grid.tick();
+        assert_eq!(grid.periodicity(), None);
+        grid.tick();
+        assert_eq!(grid.periodicity(), Some(Periodicity::Stable));
+    }
+
+    #[test]
+    fn snapshots_show_the_found_repetition() {
+        let mut blinker = grid((5, 5), "2$b3o!", "B3/S23");
+        blinker.set_period_detection(true);
+        for _ in 0..3 {
+            blinker.tick();
+        }
+        let found = Some(Periodicity::Oscillator { period: 2 });
+        assert_eq!(blinker.periodicity(), found);
+        assert_eq!(blinker.snapshot().periodicity(), found);
+    }
+
+    #[test]
+    fn only_detects_periods_when_enabled() {
+        let mut block = grid((6, 6), "2$2b2o$2b2o!", "B3/S23");
+        block.tick();
+        block.tick();
+        assert_eq!(block.periodicity(), None);
+        assert!(block.periods.is_empty());
+    }
+
+    #[test]
+    fn generations_patterns_keep_their_dying_cells_in_rle() {
+        let mut brain = grid((12, 12), "4$4b2o$4b2o!", "/2/3");
+        for _ in 0..5 {
+            brain.tick();
+        }
+        let text = rle::parse(&brain.to_rle().unwrap()).expect("Saved pattern is valid");
+        let bounds = brain.bounds();
+        let reloaded = Grid::new(
+            text,
+            brain.rule,
+            Boundary::Plane,
+            GridDrawSettings::default(),
+        );
+
+        let cells = |grid: &Grid| {
+            all_coords(bounds.height as usize, bounds.width as usize)
+                .map(|(y, x)| grid.cell_at(y as i64, x as i64))
+                .collect::<Vec<_>>()
+        };
+        let saved = cells(&brain);
+        assert!(saved.iter().any(|cell| matches!(cell, LifeCell::Dying(_))));
+        assert_eq!(reloaded.bounds(), bounds);
+        assert_eq!(cells(&reloaded), saved);
+    }
+
+    /// Conway's life calculated cell by cell with the neighbours the boundary resolves.
+    fn reference_step(alive: &[Vec<bool>], boundary: Boundary) -> Vec<Vec<bool>> {
+        let (height, width) = (alive.len(), alive[0].len());
+        let mut next = alive.to_vec();
+        for (y, x) in all_coords(height, width) {
+            let neighbours = NEIGHBOUR_OFFSETS
+                .iter()
+                .filter_map(|(dy, dx)| {
+                    boundary.resolve(height, width, y as isize + dy, x as isize + dx)
+                })
+                .filter(|&(y, x)| alive[y][x])
+                .count();
+            next[y][x] = neighbours == 3 || alive[y][x] && neighbours == 2;
+        }
+        next
+    }
+
+    #[test]
+    fn every_boundary_steps_like_a_cell_by_cell_reference() {
+        for (height, width) in [(9, 9), (7, 70)] {
+            let mut state = 0x9e37_79b9_7f4a_7c15_u64;
+            let alive: Vec<Vec<bool>> = (0..height)
+                .map(|_| {
+                    (0..width)
+                        .map(|_| {
+                            state ^= state << 13;
+                            state ^= state >> 7;
+                            state ^= state << 17;
+                            state.is_multiple_of(3)
+                        })
+                        .collect()
+                })
+                .collect();
+            let rows: Vec<String> = alive
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|&cell| if cell { 'o' } else { 'b' })
+                        .collect()
+                })
+                .collect();
+            let text = format!("x = {}, y = {}\n{}!", width, height, rows.join("$"));
+
+            for boundary in Boundary::ALL {
+                if boundary == Boundary::Sphere && height != width {
+                    continue;
+                }
+                for parallel in [false, true] {
+                    let text = rle::parse(&text).expect("Pattern is valid");
+                    let mut grid =
+                        Grid::new(text, Rule::conway(), boundary, GridDrawSettings::default());
+                    grid.set_parallel(parallel);
+                    let mut expected = alive.clone();
+                    for generation in 1..=4 {
+                        grid.tick();
+                        expected = reference_step(&expected, boundary);
+                        let cells: Vec<Vec<bool>> = (0..height)
+                            .map(|y| {
+                                (0..width)
+                                    .map(|x| grid.cell_at(y as i64, x as i64) == LifeCell::Alive)
+                                    .collect()
+                            })
+                            .collect();
+                        assert_eq!(
+                            cells, expected,
+                            "{} of {} x {}, generation {}",
+                            boundary, height, width, generation
+                        );
+                    }
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,265 @@
+use std::{ops::Range, sync::Arc};
+
+use eframe::epaint::Color32;
+
+use super::{periodicity::pack_bytes, Boundary, NEIGHBOUR_OFFSETS};
+
+mod wireworld;
+
+pub use wireworld::Wireworld;
+
+/// Hand written cellular automaton whose cells have a few numbered states.
+/// The next state of a cell only depends on its own state and the states of its eight neighbours.
+pub trait Automaton: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Names of all states by their number.
+    /// State 0 is the empty state which all cells outside of the grid have.
+    fn state_names(&self) -> &'static [&'static str];
+
+    /// Suggested char for every state in text files.
+    fn state_chars(&self) -> &'static [char];
+
+    /// Colour of every state.
+    fn state_colors(&self) -> &'static [Color32];
+
+    /// Neighbours are in the order of top left to bottom right without the cell itself.
+    fn next_state(&self, state: u8, neighbours: [u8; 8]) -> u8;
+
+    fn states(&self) -> u8 {
+        self.state_names().len() as u8
+    }
+}
+
+/// Returns the built in automaton with the given name ignoring case.
+pub fn built_in(name: &str) -> Option<Arc<dyn Automaton>> {
+    let built_in: [Arc<dyn Automaton>; 1] = [Arc::new(Wireworld)];
+    built_in
+        .into_iter()
+        .find(|automaton| automaton.name().eq_ignore_ascii_case(name.trim()))
+}
+
+/// States of the cells of a grid which runs a hand written automaton.
+#[derive(Clone)]
+pub struct AutomatonCells {
+    automaton: Arc<dyn Automaton>,
+    height: usize,
+    width: usize,
+    states: Vec<u8>,
+    next_states: Vec<u8>,
+}
+
+impl AutomatonCells {
+    pub fn new(automaton: Arc<dyn Automaton>, height: usize, width: usize) -> Self {
+        Self {
+            automaton,
+            height,
+            width,
+            states: vec![0; height * width],
+            next_states: vec![0; height * width],
+        }
+    }
+
+    /// Copy without the buffer of the next states, which [`Self::restore_buffer`] brings back.
+    pub fn without_buffer(&self) -> Self {
+        Self {
+            automaton: self.automaton.clone(),
+            height: self.height,
+            width: self.width,
+            states: self.states.clone(),
+            next_states: Vec::new(),
+        }
+    }
+
+    pub fn restore_buffer(&mut self) {
+        self.next_states = vec![0; self.states.len()];
+    }
+
+    pub fn stored_bytes(&self) -> usize {
+        self.states.len() + self.next_states.len()
+    }
+
+    pub fn automaton(&self) -> &dyn Automaton {
+        self.automaton.as_ref()
+    }
+
+    pub fn get(&self, y: usize, x: usize) -> u8 {
+        self.states[super::y_x_to_index(self.width, y, x)]
+    }
+
+    pub fn set(&mut self, y: usize, x: usize, state: u8) {
+        self.states[super::y_x_to_index(self.width, y, x)] = state;
+    }
+
+    pub fn count_non_empty(&self) -> u64 {
+        self.states.iter().filter(|&&state| state != 0).count() as u64
+    }
+
+    /// Rows and columns of the smallest rectangle with all cells which are not empty.
+    pub fn bounding_box(&self) -> Option<(Range<usize>, Range<usize>)> {
+        let mut area: Option<(Range<usize>, Range<usize>)> = None;
+        for (y, row) in self.states.chunks(self.width.max(1)).enumerate() {
+            let (Some(left), Some(last)) = (
+                row.iter().position(|&state| state != 0),
+                row.iter().rposition(|&state| state != 0),
+            ) else {
+                continue;
+            };
+            area = Some(match area {
+                Some((rows, columns)) => (
+                    rows.start..y + 1,
+                    columns.start.min(left)..columns.end.max(last + 1),
+                ),
+                None => (y..y + 1, left..last + 1),
+            });
+        }
+        area
+    }
+
+    /// Appends the states of the cells in the area row by row, packed eight per word.
+    pub fn append_area(&self, rows: Range<usize>, columns: Range<usize>, output: &mut Vec<u64>) {
+        for y in rows {
+            let row = super::y_x_to_index(self.width, y, 0);
+            let states = &self.states[row + columns.start..row + columns.end];
+            pack_bytes(states, output);
+        }
+    }
+
+    /// Calculates the next states of all cells, split into bands of rows over the given threads.
+    pub fn step(&mut self, boundary: Boundary, threads: usize) {
+        let band_height = self.height.div_ceil(threads.max(1)).max(1);
+        let band_cells = (band_height * self.width).max(1);
+        let mut next_states = std::mem::take(&mut self.next_states);
+
+        if threads <= 1 {
+            self.step_rows(0..self.height, &mut next_states, boundary);
+        } else {
+            std::thread::scope(|scope| {
+                for (band, next) in next_states.chunks_mut(band_cells).enumerate() {
+                    let start = band * band_height;
+                    let end = (start + band_height).min(self.height);
+                    let this = &*self;
+                    scope.spawn(move || this.step_rows(start..end, next, boundary));
+                }
+            });
+        }
+
+        self.next_states = next_states;
+        std::mem::swap(&mut self.states, &mut self.next_states);
+    }
+
+    fn step_rows(&self, rows: Range<usize>, next: &mut [u8], boundary: Boundary) {
+        let (height, width) = (self.height, self.width);
+        let first_row = rows.start;
+        for y in rows {
+            for x in 0..width {
+                let neighbours = NEIGHBOUR_OFFSETS.map(|(offset_y, offset_x)| {
+                    boundary
+                        .resolve(height, width, y as isize + offset_y, x as isize + offset_x)
+                        .map_or(0, |(y, x)| self.get(y, x))
+                });
+                next[(y - first_row) * width + x] =
+                    self.automaton.next_state(self.get(y, x), neighbours);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cells of Wireworld given by the chars of their states.
+    fn wireworld(rows: &[&str]) -> AutomatonCells {
+        let automaton = Arc::new(Wireworld);
+        let chars = automaton.state_chars();
+        let mut cells = AutomatonCells::new(automaton, rows.len(), rows[0].len());
+        for (y, row) in rows.iter().enumerate() {
+            for (x, char) in row.chars().enumerate() {
+                let state = chars.iter().position(|&known| known == char).unwrap();
+                cells.set(y, x, state as u8);
+            }
+        }
+        cells
+    }
+
+    fn rows(cells: &AutomatonCells) -> Vec<String> {
+        let chars = cells.automaton().state_chars();
+        (0..cells.height)
+            .map(|y| {
+                (0..cells.width)
+                    .map(|x| chars[cells.get(y, x) as usize])
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// True if an electron head reaches the column within the generations.
+    fn reaches(mut cells: AutomatonCells, column: usize, generations: usize) -> bool {
+        (0..generations).any(|_| {
+            cells.step(Boundary::Plane, 1);
+            (0..cells.height).any(|y| cells.get(y, column) == 1)
+        })
+    }
+
+    #[test]
+    fn electrons_move_along_wires() {
+        let mut cells = wireworld(&["tH####"]);
+        cells.step(Boundary::Plane, 1);
+        assert_eq!(rows(&cells), ["#tH###"]);
+        cells.step(Boundary::Plane, 1);
+        assert_eq!(rows(&cells), ["##tH##"]);
+
+        // The end of the wire is joined to its start on a torus but not on a plane.
+        let mut plane = wireworld(&["......", "tH####", "......"]);
+        let mut torus = plane.clone();
+        for _ in 0..6 {
+            plane.step(Boundary::Plane, 1);
+            torus.step(Boundary::Torus, 1);
+        }
+        assert_eq!(rows(&torus), ["......", "tH####", "......"]);
+        assert_eq!(rows(&plane), ["......", "######", "......"]);
+    }
+
+    #[test]
+    fn diodes_let_electrons_through_in_one_direction() {
+        let forward = wireworld(&["...##......", "tH##.#####.", "...##......"]);
+        assert!(reaches(forward, 9, 14));
+        let backward = wireworld(&["...##......", "####.####Ht", "...##......"]);
+        assert!(!reaches(backward, 1, 14));
+    }
+
+    #[test]
+    fn parallel_steps_equal_serial_steps() {
+        let mut state = 0xbb67_ae85_84ca_a73b_u64;
+        let mut cells = AutomatonCells::new(Arc::new(Wireworld), 37, 50);
+        for (y, x) in super::super::all_coords(37, 50) {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            // Mostly conductors with a few electrons so that many heads are born.
+            let cell = match state % 10 {
+                0 => 1,
+                1 => 2,
+                2..=6 => 3,
+                _ => 0,
+            };
+            cells.set(y, x, cell);
+        }
+
+        for boundary in Boundary::ALL {
+            if boundary == Boundary::Sphere {
+                continue;
+            }
+            let mut serial = cells.clone();
+            let mut parallel: Vec<AutomatonCells> = vec![cells.clone(); 3];
+            for _ in 0..5 {
+                serial.step(boundary, 1);
+                for (threads, cells) in [2, 3, 8].into_iter().zip(&mut parallel) {
+                    cells.step(boundary, threads);
+                    assert_eq!(cells.states, serial.states, "{} on {}", threads, boundary);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use eframe::epaint::Color32;
+
+use super::Automaton;
+
+const EMPTY: u8 = 0;
+const HEAD: u8 = 1;
+const TAIL: u8 = 2;
+const CONDUCTOR: u8 = 3;
+
+/// Electrons made of a head and a tail which travel along conductors.
+/// Used to build logic circuits like diodes and gates.
+pub struct Wireworld;
+
+impl Automaton for Wireworld {
+    fn name(&self) -> &'static str {
+        "Wireworld"
+    }
+
+    fn state_names(&self) -> &'static [&'static str] {
+        &["empty", "electron head", "electron tail", "conductor"]
+    }
+
+    fn state_chars(&self) -> &'static [char] {
+        &['.', 'H', 't', '#']
+    }
+
+    fn state_colors(&self) -> &'static [Color32] {
+        const COLORS: [Color32; 4] = [
+            Color32::BLACK,
+            Color32::from_rgb(0, 128, 255),
+            Color32::WHITE,
+            Color32::from_rgb(255, 128, 0),
+        ];
+        &COLORS
+    }
+
+    fn next_state(&self, state: u8, neighbours: [u8; 8]) -> u8 {
+        match state {
+            HEAD => TAIL,
+            TAIL => CONDUCTOR,
+            CONDUCTOR => {
+                let heads = neighbours.iter().filter(|&&state| state == HEAD).count();
+                if heads == 1 || heads == 2 {
+                    HEAD
+                } else {
+                    CONDUCTOR
+                }
+            }
+            _ => EMPTY,
+        }
+    }
+}
@@ -0,0 +1,401 @@
+use std::ops::Range;
+
+use super::{CountConditions, Rule};
+
+const WORD_BITS: usize = u64::BITS as usize;
+/// Neighbour counts from 0 to 8 need 4 bits.
+const COUNT_PLANES: usize = 4;
+
+/// Alive cells packed as one bit per cell, 64 cells per word.
+/// Each row starts at a new word and unused bits at the end of a row are always zero.
+#[derive(Clone)]
+pub struct BitBoard {
+    height: usize,
+    width: usize,
+    words_per_row: usize,
+    words: Vec<u64>,
+}
+
+impl BitBoard {
+    pub fn new(height: usize, width: usize) -> Self {
+        let words_per_row = width.div_ceil(WORD_BITS);
+        Self {
+            height,
+            width,
+            words_per_row,
+            words: vec![0; words_per_row * height],
+        }
+    }
+
+    /// Index of the bit of a cell when all words are put one after another.
+    pub fn bit_index(&self, y: usize, x: usize) -> usize {
+        y * self.words_per_row * WORD_BITS + x
+    }
+
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    pub fn words_mut(&mut self) -> &mut [u64] {
+        &mut self.words
+    }
+
+    pub fn stored_bytes(&self) -> usize {
+        std::mem::size_of_val(self.words.as_slice())
+    }
+
+    pub fn get(&self, y: usize, x: usize) -> bool {
+        let word = self.words[y * self.words_per_row + x / WORD_BITS];
+        (word >> (x % WORD_BITS)) & 1 == 1
+    }
+
+    pub fn set(&mut self, y: usize, x: usize, alive: bool) {
+        let word = &mut self.words[y * self.words_per_row + x / WORD_BITS];
+        let mask = 1 << (x % WORD_BITS);
+        if alive {
+            *word |= mask;
+        } else {
+            *word &= !mask;
+        }
+    }
+
+    pub fn count_alive(&self) -> u64 {
+        self.words.iter().map(|word| word.count_ones() as u64).sum()
+    }
+
+    /// Rows and columns of the smallest rectangle with all alive cells.
+    pub fn bounding_box(&self) -> Option<(Range<usize>, Range<usize>)> {
+        let mut area: Option<(Range<usize>, Range<usize>)> = None;
+        for y in 0..self.height {
+            let row = self.row(y);
+            let (Some(first), Some(last)) = (
+                row.iter().position(|&word| word != 0),
+                row.iter().rposition(|&word| word != 0),
+            ) else {
+                continue;
+            };
+            let left = first * WORD_BITS + row[first].trailing_zeros() as usize;
+            let right = (last + 1) * WORD_BITS - row[last].leading_zeros() as usize;
+            area = Some(match area {
+                Some((rows, columns)) => (
+                    rows.start..y + 1,
+                    columns.start.min(left)..columns.end.max(right),
+                ),
+                None => (y..y + 1, left..right),
+            });
+        }
+        area
+    }
+
+    /// Appends the bits of the cells in the area, every row starting at a new word.
+    pub fn append_area(&self, rows: Range<usize>, columns: Range<usize>, output: &mut Vec<u64>) {
+        let width = columns.len();
+        for y in rows {
+            let row = self.row(y);
+            for start in (0..width).step_by(WORD_BITS) {
+                let bit = columns.start + start;
+                let (index, shift) = (bit / WORD_BITS, bit % WORD_BITS);
+                let mut word = row[index] >> shift;
+                if shift > 0 {
+                    if let Some(next) = row.get(index + 1) {
+                        word |= next << (WORD_BITS - shift);
+                    }
+                }
+                let used = (width - start).min(WORD_BITS);
+                if used < WORD_BITS {
+                    word &= (1 << used) - 1;
+                }
+                output.push(word);
+            }
+        }
+    }
+
+    fn row(&self, y: usize) -> &[u64] {
+        let start = y * self.words_per_row;
+        &self.words[start..start + self.words_per_row]
+    }
+
+    /// Mask for the bits of the last word in a row which belong to cells.
+    fn last_word_mask(&self) -> u64 {
+        match self.width % WORD_BITS {
+            0 => u64::MAX,
+            used => (1 << used) - 1,
+        }
+    }
+
+    /// Writes the next generation into `next` by counting 64 neighbourhoods at once per word.
+    /// With `wrap` the edges are joined like a torus, otherwise everything outside is dead.
+    pub fn step_into(&self, next: &mut BitBoard, rule: &Rule, wrap: bool) {
+        debug_assert_eq!(self.words.len(), next.words.len());
+        self.step_rows(0..self.height, &mut next.words, rule, wrap);
+    }
+
+    /// Same as [`BitBoard::step_into`] but splits the rows into bands
+    /// which are calculated on several threads at once.
+    pub fn step_into_parallel(&self, next: &mut BitBoard, rule: &Rule, wrap: bool, threads: usize) {
+        debug_assert_eq!(self.words.len(), next.words.len());
+        let band_height = self.height.div_ceil(threads.max(1)).max(1);
+        let band_words = (band_height * self.words_per_row).max(1);
+
+        std::thread::scope(|scope| {
+            for (band, next_words) in next.words.chunks_mut(band_words).enumerate() {
+                let start = band * band_height;
+                let end = (start + band_height).min(self.height);
+                scope.spawn(move || self.step_rows(start..end, next_words, rule, wrap));
+            }
+        });
+    }
+
+    /// Sets every cell to the result of `is_alive` for its row and column.
+    /// The rows are split into bands which are filled on the given number of threads.
+    pub fn fill_with(&mut self, threads: usize, is_alive: impl Fn(usize, usize) -> bool + Sync) {
+        let (width, words_per_row) = (self.width, self.words_per_row);
+        if words_per_row == 0 {
+            return;
+        }
+
+        let is_alive = &is_alive;
+        let fill_band = move |first_row: usize, words: &mut [u64]| {
+            for (row, row_words) in words.chunks_mut(words_per_row).enumerate() {
+                for (index, word) in row_words.iter_mut().enumerate() {
+                    let first_x = index * WORD_BITS;
+                    *word = (first_x..(first_x + WORD_BITS).min(width))
+                        .filter(|&x| is_alive(first_row + row, x))
+                        .fold(0, |word, x| word | 1 << (x - first_x));
+                }
+            }
+        };
+
+        if threads <= 1 {
+            fill_band(0, &mut self.words);
+            return;
+        }
+        let band_height = self.height.div_ceil(threads).max(1);
+        std::thread::scope(|scope| {
+            for (band, words) in self
+                .words
+                .chunks_mut(band_height * words_per_row)
+                .enumerate()
+            {
+                scope.spawn(move || fill_band(band * band_height, words));
+            }
+        });
+    }
+
+    /// Calculates the given rows into `next_words` which only holds the words of these rows.
+    fn step_rows(&self, rows: Range<usize>, next_words: &mut [u64], rule: &Rule, wrap: bool) {
+        let (height, words_per_row) = (self.height, self.words_per_row);
+        if height == 0 || words_per_row == 0 {
+            return;
+        }
+
+        let conditions = rule.count_conditions();
+        let empty_row = vec![0; words_per_row];
+        let last_mask = self.last_word_mask();
+        let first_row = rows.start;
+
+        for y in rows {
+            let above = match (y, wrap) {
+                (0, true) => self.row(height - 1),
+                (0, false) => &empty_row,
+                (y, _) => self.row(y - 1),
+            };
+            let below = match (y + 1 == height, wrap) {
+                (true, true) => self.row(0),
+                (true, false) => &empty_row,
+                (false, _) => self.row(y + 1),
+            };
+            let current = self.row(y);
+            let band_y = y - first_row;
+            let next_row = &mut next_words[band_y * words_per_row..(band_y + 1) * words_per_row];
+
+            for (index, next_word) in next_row.iter_mut().enumerate() {
+                let neighbourhood =
+                    [above, current, below].map(|row| self.shifted_neighbours(row, index, wrap));
+                let mut word = next_word_of(neighbourhood, &conditions);
+                if index + 1 == words_per_row {
+                    word &= last_mask;
+                }
+                *next_word = word;
+            }
+        }
+    }
+
+    /// Returns the word of the row with every bit moved to its east and west neighbour,
+    /// so that bit x of the results holds the cells at x - 1, x and x + 1.
+    fn shifted_neighbours(&self, row: &[u64], index: usize, wrap: bool) -> (u64, u64, u64) {
+        let last_index = self.words_per_row - 1;
+        let last_bit = (self.width - 1) % WORD_BITS;
+        let centre = row[index];
+
+        let carry_from_west = if index > 0 {
+            row[index - 1] >> (WORD_BITS - 1)
+        } else if wrap {
+            (row[last_index] >> last_bit) & 1
+        } else {
+            0
+        };
+        let carry_from_east = if index < last_index {
+            (row[index + 1] & 1) << (WORD_BITS - 1)
+        } else if wrap {
+            (row[0] & 1) << last_bit
+        } else {
+            0
+        };
+
+        let west = (centre << 1) | carry_from_west;
+        let east = (centre >> 1) | carry_from_east;
+        (west, centre, east)
+    }
+}
+
+/// Calculates the next generation of the 64 cells in the middle word.
+/// Every row above, at and below the cells is given as its word shifted to the west, unshifted
+/// and shifted to the east, so bit x of the three words holds the cells at x - 1, x and x + 1.
+pub fn next_word_of(neighbourhood: [(u64, u64, u64); 3], conditions: &CountConditions) -> u64 {
+    let mut counts = [0; COUNT_PLANES];
+    for (row, (west, centre, east)) in neighbourhood.into_iter().enumerate() {
+        add_to_counts(&mut counts, west);
+        add_to_counts(&mut counts, east);
+        if row != 1 {
+            add_to_counts(&mut counts, centre);
+        }
+    }
+
+    let alive = neighbourhood[1].1;
+    let (mut born, mut survives) = (0, 0);
+    for count in 0..=8 {
+        let with_count = count_equals(&counts, count);
+        if conditions.birth[count] {
+            born |= with_count;
+        }
+        if conditions.survival[count] {
+            survives |= with_count;
+        }
+    }
+
+    (alive & survives) | (!alive & born)
+}
+
+/// Adds one bit to every of the 64 bit sliced counters with a ripple carry.
+fn add_to_counts(counts: &mut [u64; COUNT_PLANES], bits: u64) {
+    let mut carry = bits;
+    for plane in counts.iter_mut() {
+        let next_carry = *plane & carry;
+        *plane ^= carry;
+        carry = next_carry;
+    }
+}
+
+/// Returns a mask of the counters which equal the given count.
+fn count_equals(counts: &[u64; COUNT_PLANES], count: usize) -> u64 {
+    counts
+        .iter()
+        .enumerate()
+        .fold(u64::MAX, |mask, (bit, plane)| {
+            if (count >> bit) & 1 == 1 {
+                mask & plane
+            } else {
+                mask & !plane
+            }
+        })
+}
+
+/// Returns the index of every bit which is set, starting with the lowest.
+pub fn set_bits(mut word: u64) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        (word != 0).then(|| {
+            let bit = word.trailing_zeros() as usize;
+            word &= word - 1;
+            bit
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_area_of_alive_cells_across_words() {
+        let mut board = BitBoard::new(4, 150);
+        assert_eq!(board.bounding_box(), None);
+        for (y, x) in [(1, 62), (1, 66), (2, 130), (3, 63)] {
+            board.set(y, x, true);
+        }
+        let (rows, columns) = board.bounding_box().expect("Board has alive cells");
+        assert_eq!((rows.clone(), columns.clone()), (1..4, 62..131));
+
+        let mut area = Vec::new();
+        board.append_area(rows, columns, &mut area);
+        // 69 columns take two words per row.
+        assert_eq!(area, vec![0b10001, 0, 0, 0b10000, 0b10, 0]);
+    }
+
+    /// Board with about a third of the cells alive, the same for every run.
+    fn random_board(height: usize, width: usize, seed: u64) -> BitBoard {
+        let mut board = BitBoard::new(height, width);
+        let mut state = seed | 1;
+        for y in 0..height {
+            for x in 0..width {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                board.set(y, x, state.is_multiple_of(3));
+            }
+        }
+        board
+    }
+
+    /// Next generation of Conway's life calculated cell by cell.
+    fn reference_step(board: &BitBoard, wrap: bool) -> BitBoard {
+        let (height, width) = (board.height as isize, board.width as isize);
+        let mut next = BitBoard::new(board.height, board.width);
+        for y in 0..height {
+            for x in 0..width {
+                let neighbours = (-1..=1)
+                    .flat_map(|dy| (-1..=1).map(move |dx| (dy, dx)))
+                    .filter(|&offset| offset != (0, 0))
+                    .filter(|&(dy, dx)| {
+                        let (y, x) = (y + dy, x + dx);
+                        if wrap {
+                            board.get(y.rem_euclid(height) as usize, x.rem_euclid(width) as usize)
+                        } else {
+                            (0..height).contains(&y)
+                                && (0..width).contains(&x)
+                                && board.get(y as usize, x as usize)
+                        }
+                    })
+                    .count();
+                let alive = board.get(y as usize, x as usize);
+                next.set(
+                    y as usize,
+                    x as usize,
+                    neighbours == 3 || alive && neighbours == 2,
+                );
+            }
+        }
+        next
+    }
+
+    #[test]
+    fn parallel_steps_equal_serial_steps() {
+        let rule = Rule::conway();
+        let sizes = [(1, 1), (7, 63), (33, 64), (40, 100), (65, 130), (20, 200)];
+        for (index, (height, width)) in sizes.into_iter().enumerate() {
+            let board = random_board(height, width, index as u64 + 7);
+            for wrap in [false, true] {
+                let mut serial = BitBoard::new(height, width);
+                board.step_into(&mut serial, &rule, wrap);
+                let reference = reference_step(&board, wrap);
+                assert_eq!(serial.words, reference.words, "{} x {}", height, width);
+
+                for threads in [2, 3, 8] {
+                    let mut parallel = BitBoard::new(height, width);
+                    board.step_into_parallel(&mut parallel, &rule, wrap, threads);
+                    assert_eq!(parallel.words, serial.words, "{} threads", threads);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use std::fmt::Display;
+
+use thiserror::Error;
+
+use super::Rule;
+
+#[derive(Debug, Error)]
+pub enum InvalidBoundary {
+    #[error("Sphere boundary needs a square grid, but grid is {height} x {width}")]
+    SphereNotSquare { height: usize, width: usize },
+    #[error("Hexagonal grids can only be a plane or a torus, not a {0}")]
+    HexagonalTwisted(Boundary),
+    #[error("Hexagonal torus needs an even number of rows, but grid has {0}")]
+    HexagonalOddHeight(usize),
+    #[error("Sphere boundary only joins cells one step apart, so it can not run radius {0}")]
+    SphereRadius(u32),
+    #[error("Radius {radius} would wrap around the {height} x {width} grid more than once")]
+    RadiusTooLarge {
+        radius: u32,
+        height: usize,
+        width: usize,
+    },
+}
+
+/// How the edges of the grid are connected to each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Boundary {
+    /// Everything outside of the grid counts as dead.
+    Plane,
+    /// Left joins right and top joins bottom.
+    #[default]
+    Torus,
+    /// Left joins right, top joins bottom with a twist.
+    KleinBottle,
+    /// Left joins right and top joins bottom, both with a twist.
+    CrossSurface,
+    /// Top joins left and bottom joins right. Only for square grids.
+    Sphere,
+}
+
+impl Boundary {
+    pub const ALL: [Boundary; 5] = [
+        Boundary::Plane,
+        Boundary::Torus,
+        Boundary::KleinBottle,
+        Boundary::CrossSurface,
+        Boundary::Sphere,
+    ];
+
+    /// Odd rows of hexagonal grids are shifted half a cell, so joined edges would not fit
+    /// if the twisted edges mirrored that shift or two rows of the same kind met.
+    /// Larger than Life rules look up cells a whole radius outside of the grid, which
+    /// spheres can not map and joined edges would count more than once if the grid is smaller.
+    /// Hand written automata pass no rule.
+    pub fn validate(
+        self,
+        height: usize,
+        width: usize,
+        rule: Option<Rule>,
+    ) -> Result<(), InvalidBoundary> {
+        let hexagonal = rule.is_some_and(|rule| rule.is_hexagonal());
+        let radius = rule
+            .and_then(|rule| rule.larger_than_life())
+            .map(|larger_than_life| larger_than_life.radius());
+        match (self, radius) {
+            (Boundary::Sphere, Some(radius)) if radius > 1 => {
+                return Err(InvalidBoundary::SphereRadius(radius));
+            }
+            (Boundary::Plane, _) | (_, None) => (),
+            (_, Some(radius)) if radius as usize >= height.min(width) => {
+                return Err(InvalidBoundary::RadiusTooLarge {
+                    radius,
+                    height,
+                    width,
+                });
+            }
+            _ => (),
+        }
+
+        match self {
+            Boundary::Sphere if height != width => {
+                Err(InvalidBoundary::SphereNotSquare { height, width })
+            }
+            _ if hexagonal && self.is_twisted() => Err(InvalidBoundary::HexagonalTwisted(self)),
+            Boundary::Torus if hexagonal && !height.is_multiple_of(2) => {
+                Err(InvalidBoundary::HexagonalOddHeight(height))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// True for topologies whose edges are joined with a twist or to another edge.
+    pub fn is_twisted(self) -> bool {
+        matches!(
+            self,
+            Boundary::KleinBottle | Boundary::CrossSurface | Boundary::Sphere
+        )
+    }
+
+    /// Maps a position outside of the grid to the cell it refers to.
+    /// Returns none if the position refers to no cell at all.
+    /// Spheres only map positions up to one step outside of the grid, see [`Boundary::validate`].
+    pub fn resolve(
+        self,
+        height: usize,
+        width: usize,
+        y: isize,
+        x: isize,
+    ) -> Option<(usize, usize)> {
+        let (h, w) = (height as isize, width as isize);
+        let outside_y = |y: isize| y < 0 || y >= h;
+        let outside_x = |x: isize| x < 0 || x >= w;
+        // Every crossing of a twisted edge mirrors the position once more.
+        let is_twisted = |position: isize, size: isize| position.div_euclid(size) % 2 != 0;
+
+        let (y, x) = match self {
+            Boundary::Plane => (y, x),
+            Boundary::Torus => (y.rem_euclid(h), x.rem_euclid(w)),
+            Boundary::KleinBottle => {
+                let (y, x) = if is_twisted(y, h) {
+                    (y.rem_euclid(h), w - 1 - x)
+                } else {
+                    (y.rem_euclid(h), x)
+                };
+                (y, x.rem_euclid(w))
+            }
+            Boundary::CrossSurface => {
+                let (y, x) = if is_twisted(y, h) {
+                    (y.rem_euclid(h), w - 1 - x)
+                } else {
+                    (y.rem_euclid(h), x)
+                };
+                if is_twisted(x, w) {
+                    (h - 1 - y, x.rem_euclid(w))
+                } else {
+                    (y, x.rem_euclid(w))
+                }
+            }
+            Boundary::Sphere => {
+                let mut position = (y, x);
+                // A corner is outside in both directions and needs two hops.
+                for _ in 0..2 {
+                    let (y, x) = position;
+                    position = if y < 0 {
+                        (x, 0)
+                    } else if y >= h {
+                        (x, w - 1)
+                    } else if x < 0 {
+                        (0, y)
+                    } else if x >= w {
+                        (h - 1, y)
+                    } else {
+                        break;
+                    };
+                }
+                position
+            }
+        };
+
+        if outside_y(y) || outside_x(x) {
+            None
+        } else {
+            Some((y as usize, x as usize))
+        }
+    }
+}
+
+impl Display for Boundary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Plane => write!(f, "Plane"),
+            Self::Torus => write!(f, "Torus"),
+            Self::KleinBottle => write!(f, "Klein bottle"),
+            Self::CrossSurface => write!(f, "Cross surface"),
+            Self::Sphere => write!(f, "Sphere"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(text: &str) -> Option<Rule> {
+        Some(text.parse().unwrap())
+    }
+
+    #[test]
+    fn sphere_rejects_larger_radius() {
+        let radius_two = rule("R2,C0,M0,S2..3,B3..3,NM");
+        assert!(matches!(
+            Boundary::Sphere.validate(10, 10, radius_two),
+            Err(InvalidBoundary::SphereRadius(2))
+        ));
+        let radius_one = rule("R1,C0,M0,S2..3,B3..3,NM");
+        assert!(Boundary::Sphere.validate(10, 10, radius_one).is_ok());
+        assert!(Boundary::Sphere.validate(10, 10, rule("B3/S23")).is_ok());
+    }
+
+    #[test]
+    fn joined_edges_reject_radius_as_large_as_grid() {
+        let radius_five = rule("R5,C0,M1,S34..58,B34..45,NM");
+        for boundary in [
+            Boundary::Torus,
+            Boundary::KleinBottle,
+            Boundary::CrossSurface,
+        ] {
+            assert!(matches!(
+                boundary.validate(5, 20, radius_five),
+                Err(InvalidBoundary::RadiusTooLarge { radius: 5, .. })
+            ));
+            assert!(boundary.validate(6, 20, radius_five).is_ok());
+        }
+        assert!(Boundary::Plane.validate(2, 2, radius_five).is_ok());
+    }
+
+    #[test]
+    fn hand_written_automata_have_no_radius() {
+        assert!(Boundary::Torus.validate(1, 1, None).is_ok());
+    }
+
+    #[test]
+    fn resolves_positions_across_each_edge() {
+        let cases = [
+            (Boundary::Plane, (-1, 0), None),
+            (Boundary::Plane, (2, 5), None),
+            (Boundary::Torus, (-1, -1), Some((3, 4))),
+            (Boundary::Torus, (4, 5), Some((0, 0))),
+            (Boundary::KleinBottle, (-1, 0), Some((3, 4))),
+            (Boundary::KleinBottle, (4, 1), Some((0, 3))),
+            (Boundary::KleinBottle, (2, -1), Some((2, 4))),
+            (Boundary::CrossSurface, (-1, 1), Some((3, 3))),
+            (Boundary::CrossSurface, (2, -1), Some((1, 4))),
+            (Boundary::CrossSurface, (2, 5), Some((1, 0))),
+        ];
+        for (boundary, (y, x), expected) in cases {
+            assert_eq!(
+                boundary.resolve(4, 5, y, x),
+                expected,
+                "{} {:?}",
+                boundary,
+                (y, x)
+            );
+        }
+
+        let sphere = [
+            ((-1, 2), (2, 0)),
+            ((1, -1), (0, 1)),
+            ((4, 2), (2, 3)),
+            ((1, 4), (3, 1)),
+        ];
+        for ((y, x), expected) in sphere {
+            assert_eq!(Boundary::Sphere.resolve(4, 4, y, x), Some(expected));
+        }
+    }
+}
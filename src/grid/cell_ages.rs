@@ -0,0 +1,98 @@
+use super::bit_board::{set_bits, BitBoard};
+
+const WORD_BITS: usize = u64::BITS as usize;
+/// Generations a cell which died is remembered as a trail.
+pub const TRAIL_LENGTH: u8 = 16;
+
+/// Ages of alive cells and trails of recently died cells kept next to the bit board of alive cells.
+#[derive(Clone)]
+pub struct CellAges {
+    /// Generations every alive cell has been alive for by the index of its bit, 0 for dead cells.
+    ages: Vec<u16>,
+    /// Generations since every cell of a trail died by the index of its bit, 0 without a trail.
+    since_death: Vec<u8>,
+    /// Bit for every cell of a trail at the same place as in the board of alive cells.
+    trails: BitBoard,
+}
+
+impl CellAges {
+    /// Alive cells start with age 1 as if they were just born.
+    pub fn new(cells: &BitBoard, height: usize, width: usize) -> Self {
+        let trails = BitBoard::new(height, width);
+        let bits = trails.words().len() * WORD_BITS;
+        let mut ages = vec![0; bits];
+        for (index, &word) in cells.words().iter().enumerate() {
+            for bit in set_bits(word) {
+                ages[index * WORD_BITS + bit] = 1;
+            }
+        }
+        Self {
+            ages,
+            since_death: vec![0; bits],
+            trails,
+        }
+    }
+
+    pub fn age(&self, y: usize, x: usize) -> u16 {
+        self.ages[self.trails.bit_index(y, x)]
+    }
+
+    /// Generations since the cell died or 0 if it has no trail.
+    pub fn since_death(&self, y: usize, x: usize) -> u8 {
+        self.since_death[self.trails.bit_index(y, x)]
+    }
+
+    /// A cell changed by hand starts over without a trail.
+    pub fn set(&mut self, y: usize, x: usize, alive: bool) {
+        let index = self.trails.bit_index(y, x);
+        self.ages[index] = u16::from(alive);
+        self.since_death[index] = 0;
+        self.trails.set(y, x, false);
+    }
+
+    /// Moves from the generation in `cells` to the one in `next`.
+    /// Surviving cells get older, born cells start with age 1
+    /// and cells which died start a trail which fades over `TRAIL_LENGTH` generations.
+    pub fn advance(&mut self, cells: &BitBoard, next: &BitBoard) {
+        let Self {
+            ages,
+            since_death,
+            trails,
+        } = self;
+        let words = cells
+            .words()
+            .iter()
+            .zip(next.words())
+            .zip(trails.words_mut());
+
+        for (index, ((&alive, &next_word), trail_word)) in words.enumerate() {
+            let cells = index * WORD_BITS..(index + 1) * WORD_BITS;
+            let (ages, since_death) = (&mut ages[cells.clone()], &mut since_death[cells]);
+
+            let mut still_fading = 0;
+            for bit in set_bits(*trail_word) {
+                if next_word & 1 << bit == 0 && since_death[bit] < TRAIL_LENGTH {
+                    since_death[bit] += 1;
+                    still_fading |= 1 << bit;
+                } else {
+                    since_death[bit] = 0;
+                }
+            }
+
+            for bit in set_bits(next_word) {
+                ages[bit] = if alive & 1 << bit != 0 {
+                    ages[bit].saturating_add(1)
+                } else {
+                    1
+                };
+            }
+
+            let died = alive & !next_word;
+            for bit in set_bits(died) {
+                ages[bit] = 0;
+                since_death[bit] = 1;
+            }
+            *trail_word = still_fading | died;
+        }
+    }
+}
@@ -6,6 +6,12 @@ pub enum InvalidCharCell {
     TooLongForDead,
     #[error("Text for alive cell should only contain one char")]
     TooLongForAlive,
+    #[error("Text for {0} cell should only contain one char")]
+    TooLongForState(&'static str),
+    #[error("Char {0} is used for more than one state")]
+    DuplicateChar(char),
+    #[error("{given} chars were given for the {states} states of the automaton")]
+    WrongStateCount { given: usize, states: usize },
 }
 #[derive(CopyGetters)]
 #[getset(get_copy = "pub")]
@@ -18,15 +24,15 @@ impl DeadAliveCharCell {
     pub fn new(dead: &str, alive: &str) -> Result<Self, InvalidCharCell> {
         let dead = get_only_first_char(dead).ok_or(InvalidCharCell::TooLongForDead)?;
         let alive = get_only_first_char(alive).ok_or(InvalidCharCell::TooLongForAlive)?;
-        return Ok(Self { dead, alive });
+        Ok(Self { dead, alive })
+    }
+}
 
-        fn get_only_first_char(input: &str) -> Option<char> {
-            let input = input.trim();
-            if input.len() == 1 {
-                Some(input.chars().next().unwrap())
-            } else {
-                None
-            }
-        }
+pub fn get_only_first_char(input: &str) -> Option<char> {
+    let input = input.trim();
+    if input.len() == 1 {
+        Some(input.chars().next().unwrap())
+    } else {
+        None
     }
 }
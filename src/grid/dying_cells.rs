@@ -0,0 +1,97 @@
+use std::ops::Range;
+
+use super::{
+    bit_board::{set_bits, BitBoard},
+    periodicity::pack_bytes,
+};
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// Dying stages of generations rules kept next to the bit board of alive cells.
+#[derive(Clone)]
+pub struct DyingCells {
+    /// Bit for every dying cell at the same place as in the board of alive cells.
+    mask: BitBoard,
+    /// Stage of every cell by the index of its bit, 0 if the cell is not dying.
+    stages: Vec<u8>,
+}
+
+impl DyingCells {
+    pub fn new(height: usize, width: usize) -> Self {
+        let mask = BitBoard::new(height, width);
+        let stages = vec![0; mask.words().len() * WORD_BITS];
+        Self { mask, stages }
+    }
+
+    pub fn stage(&self, y: usize, x: usize) -> u8 {
+        self.stages[self.mask.bit_index(y, x)]
+    }
+
+    pub fn stored_bytes(&self) -> usize {
+        self.mask.stored_bytes() + self.stages.len()
+    }
+
+    /// Bit for every dying cell.
+    pub fn mask(&self) -> &BitBoard {
+        &self.mask
+    }
+
+    /// Appends the stages of the dying cells in the area row by row, packed eight per word.
+    pub fn append_stages(&self, rows: Range<usize>, columns: Range<usize>, output: &mut Vec<u64>) {
+        let mut mask = Vec::new();
+        self.mask
+            .append_area(rows.clone(), columns.clone(), &mut mask);
+        let words_per_row = columns.len().div_ceil(WORD_BITS);
+        let stages: Vec<u8> = mask
+            .iter()
+            .enumerate()
+            .flat_map(|(index, &word)| {
+                let y = rows.start + index / words_per_row;
+                let first_x = columns.start + index % words_per_row * WORD_BITS;
+                set_bits(word).map(move |bit| self.stage(y, first_x + bit))
+            })
+            .collect();
+        pack_bytes(&stages, output);
+    }
+
+    /// Stage 0 removes the cell from the dying cells.
+    pub fn set_stage(&mut self, y: usize, x: usize, stage: u8) {
+        let index = self.mask.bit_index(y, x);
+        self.stages[index] = stage;
+        self.mask.set(y, x, stage > 0);
+    }
+
+    /// Moves from the generation in `cells` to the one in `next`.
+    /// Dying cells are kept from being born, advance by one stage and are dead after the last.
+    /// Alive cells which did not survive start with the first stage.
+    pub fn advance(&mut self, cells: &BitBoard, next: &mut BitBoard, states: u8) {
+        let Self { mask, stages } = self;
+        let last_stage = states.saturating_sub(2);
+        let words = cells
+            .words()
+            .iter()
+            .zip(next.words_mut())
+            .zip(mask.words_mut());
+
+        for (index, ((&alive, next_word), dying_word)) in words.enumerate() {
+            *next_word &= !*dying_word;
+            let stages = &mut stages[index * WORD_BITS..(index + 1) * WORD_BITS];
+
+            let mut still_dying = 0;
+            for bit in set_bits(*dying_word) {
+                if stages[bit] < last_stage {
+                    stages[bit] += 1;
+                    still_dying |= 1 << bit;
+                } else {
+                    stages[bit] = 0;
+                }
+            }
+
+            let newly_dying = alive & !*next_word;
+            for bit in set_bits(newly_dying) {
+                stages[bit] = 1;
+            }
+            *dying_word = still_dying | newly_dying;
+        }
+    }
+}
@@ -1,24 +1,222 @@
-use eframe::epaint::Color32;
+use std::fmt::Display;
+
+use eframe::epaint::{Color32, Pos2, Rect, Vec2};
+
+use super::Automaton;
 
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub enum LifeCell {
     Alive,
     Dead,
+    /// Refractory state of generations rules counting up from 1.
+    /// Dying cells are no alive neighbours and can not be born.
+    Dying(u8),
+    /// Further state of a hand written automaton, numbered from 2 on.
+    State(u8),
 }
-#[derive(Clone)]
+
+impl LifeCell {
+    /// Number of the state, dead is 0, alive is 1 and dying stages follow after them.
+    pub fn state(self) -> u8 {
+        match self {
+            LifeCell::Dead => 0,
+            LifeCell::Alive => 1,
+            LifeCell::Dying(stage) => stage + 1,
+            LifeCell::State(state) => state,
+        }
+    }
+
+    /// Cell of a hand written automaton with the given state.
+    pub fn from_state(state: u8) -> Self {
+        match state {
+            0 => LifeCell::Dead,
+            1 => LifeCell::Alive,
+            state => LifeCell::State(state),
+        }
+    }
+}
+
+/// First colour of dying cells which fades into the colour of dead cells over the stages.
+const DYING_CELL_COLOR: Color32 = Color32::from_rgb(40, 90, 200);
+/// Cells this old get the last colour of the age gradient.
+const OLD_AGE: u16 = 256;
+
+/// What the colour of a cell shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Colouring {
+    /// Every state has its own colour.
+    #[default]
+    States,
+    /// Alive cells fade from the first to the last colour of the age gradient while they grow older,
+    /// so still lifes stand out from oscillators and fresh growth.
+    Age,
+    /// Cells which died recently leave a fading trail behind moving patterns.
+    Trails,
+}
+
+impl Colouring {
+    pub const ALL: [Colouring; 3] = [Colouring::States, Colouring::Age, Colouring::Trails];
+}
+
+impl Display for Colouring {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::States => write!(f, "States"),
+            Self::Age => write!(f, "Age"),
+            Self::Trails => write!(f, "Trails"),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
 pub struct GridDrawSettings {
     pub dead_cell_color: Color32,
     pub alive_cell_color: Color32,
+    /// Colour for every dying stage, starting with stage 1.
+    pub dying_cell_colors: Vec<Color32>,
+    /// Colour of every state of a hand written automaton, used instead of all colours above.
+    pub state_colors: Vec<Color32>,
     pub cell_size: usize,
     pub stroke_color: Color32,
+    /// Only engines which track the age of cells show anything else than states.
+    pub colouring: Colouring,
+    /// Colours of newborn cells and of the oldest cells.
+    pub age_gradient: [Color32; 2],
+    /// Colour of a cell which just died, fading into the colour of dead cells.
+    pub trail_color: Color32,
+}
+impl GridDrawSettings {
+    /// Gives every dying state of a rule with that many states its own colour.
+    pub fn with_states(mut self, states: u8) -> Self {
+        let dying_states = states.saturating_sub(2);
+        let faded_into = self.color_for(LifeCell::Dead);
+        self.dying_cell_colors = (0..dying_states)
+            .map(|stage| {
+                let faded = stage as f32 / dying_states as f32;
+                mix_colors(DYING_CELL_COLOR, faded_into, faded)
+            })
+            .collect();
+        self
+    }
+
+    pub fn with_automaton(mut self, automaton: &dyn Automaton) -> Self {
+        self.state_colors = automaton.state_colors().to_vec();
+        self
+    }
+
+    /// Ages grow slower the older a cell is, so young cells differ more from each other.
+    pub fn age_color(&self, age: u16) -> Color32 {
+        let amount = (age.max(1) as f32).log2() / (OLD_AGE as f32).log2();
+        mix_colors(self.age_gradient[0], self.age_gradient[1], amount.min(1.))
+    }
+
+    /// Colour of a cell which died the given number of generations ago.
+    pub fn trail_color_for(&self, since_death: u8, trail_length: u8) -> Color32 {
+        let amount = since_death as f32 / trail_length as f32;
+        mix_colors(self.trail_color, self.color_for(LifeCell::Dead), amount)
+    }
+
+    pub fn color_for(&self, cell: LifeCell) -> Color32 {
+        if let Some(&color) = self.state_colors.get(cell.state() as usize) {
+            return color;
+        }
+        match cell {
+            LifeCell::Alive => self.dead_cell_color,
+            LifeCell::Dead => self.alive_cell_color,
+            LifeCell::Dying(stage) => self
+                .dying_cell_colors
+                .get((stage as usize).saturating_sub(1))
+                .copied()
+                .unwrap_or(DYING_CELL_COLOR),
+            LifeCell::State(_) => self.stroke_color,
+        }
+    }
 }
 impl Default for GridDrawSettings {
     fn default() -> Self {
         Self {
             dead_cell_color: Color32::BLACK,
             alive_cell_color: Color32::WHITE,
+            dying_cell_colors: Vec::new(),
+            state_colors: Vec::new(),
             cell_size: 10,
             stroke_color: Color32::GRAY,
+            colouring: Colouring::States,
+            age_gradient: [
+                Color32::from_rgb(255, 140, 0),
+                Color32::from_rgb(20, 20, 120),
+            ],
+            trail_color: Color32::from_rgb(230, 60, 60),
         }
     }
 }
+
+/// Colour between `from` and `to`, where 0 is `from` and 1 is `to`.
+fn mix_colors(from: Color32, to: Color32, amount: f32) -> Color32 {
+    let mix = |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * amount) as u8;
+    Color32::from_rgb(
+        mix(from.r(), to.r()),
+        mix(from.g(), to.g()),
+        mix(from.b(), to.b()),
+    )
+}
+
+/// Where and how large the grid is drawn on the screen.
+#[derive(Clone, Copy, Debug)]
+pub struct GridTransform {
+    /// Screen position of the top left corner of the first cell.
+    pub origin: Pos2,
+    pub cell_size: f32,
+}
+
+impl GridTransform {
+    pub fn cell_min(&self, y: i64, x: i64) -> Pos2 {
+        Pos2 {
+            x: self.origin.x + x as f32 * self.cell_size,
+            y: self.origin.y + y as f32 * self.cell_size,
+        }
+    }
+
+    /// Returns the fractional row and column under the given screen position.
+    pub fn grid_position(&self, position: Pos2) -> (f32, f32) {
+        (
+            (position.y - self.origin.y) / self.cell_size,
+            (position.x - self.origin.x) / self.cell_size,
+        )
+    }
+
+    /// Returns the row and column of the cell under the given screen position.
+    pub fn cell_at_position(&self, position: Pos2) -> (i64, i64) {
+        let (y, x) = self.grid_position(position);
+        (y.floor() as i64, x.floor() as i64)
+    }
+}
+
+/// Rectangle of cells in cell coordinates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct CellBounds {
+    pub top: i64,
+    pub left: i64,
+    pub height: u64,
+    pub width: u64,
+}
+
+impl CellBounds {
+    pub fn contains(&self, y: i64, x: i64) -> bool {
+        y >= self.top
+            && x >= self.left
+            && ((y - self.top) as u64) < self.height
+            && ((x - self.left) as u64) < self.width
+    }
+
+    /// Area of the cells on the screen without any zoom, relative to the origin of the grid.
+    pub fn unscaled_rect(&self, cell_size: f32) -> Rect {
+        Rect::from_min_size(
+            Pos2::new(self.left as f32 * cell_size, self.top as f32 * cell_size),
+            Vec2::new(
+                self.width as f32 * cell_size,
+                self.height as f32 * cell_size,
+            ),
+        )
+    }
+}
@@ -0,0 +1,211 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, VecDeque},
+    fmt::Display,
+    hash::{Hash, Hasher},
+};
+
+/// Generations which are remembered, so longer periods are not found.
+const MAX_PERIOD: usize = 1024;
+/// Words of the cells of earlier generations which are kept to compare with,
+/// so huge patterns only find shorter periods instead of taking gigabytes.
+const MAX_STORED_WORDS: usize = 1 << 23;
+const BYTES_PER_WORD: usize = u64::BITS as usize / 8;
+
+/// How the whole pattern repeats itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Periodicity {
+    /// Every generation is the same, which includes a pattern which died out.
+    Stable,
+    Oscillator {
+        period: u64,
+    },
+    /// The pattern repeats after the period moved by the given columns and rows.
+    Spaceship {
+        period: u64,
+        dx: i64,
+        dy: i64,
+    },
+}
+
+impl Display for Periodicity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Stable => write!(f, "Stable"),
+            Self::Oscillator { period } => write!(f, "Period {} oscillator", period),
+            Self::Spaceship { period, dx, dy } => {
+                write!(f, "Spaceship, velocity ({}, {})/{}", dx, dy, period)
+            }
+        }
+    }
+}
+
+/// Cells of a generation relative to the top left corner of the cells which are not dead,
+/// packed into words so that they are hashed and compared quickly.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct PatternKey {
+    pub corner: (i64, i64),
+    pub cells: Vec<u64>,
+}
+
+#[derive(Clone)]
+struct Recorded {
+    generation: u64,
+    hash: u64,
+    key: PatternKey,
+}
+
+/// Finds the last generation whose cells looked the same as the current ones,
+/// possibly at another place.
+#[derive(Clone, Default)]
+pub struct PeriodDetector {
+    /// Last generation with the hash of its cells.
+    seen: HashMap<u64, u64>,
+    /// Generations in the order they were recorded, to compare with and to forget the oldest.
+    recorded: VecDeque<Recorded>,
+    stored_words: usize,
+    found: Option<Periodicity>,
+}
+
+impl PeriodDetector {
+    /// Remembers a generation. A repetition is only found if an earlier generation
+    /// with the same hash also has exactly the same cells.
+    pub fn record(&mut self, generation: u64, key: PatternKey) {
+        let mut hasher = DefaultHasher::new();
+        key.cells.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        self.found = self
+            .seen
+            .get(&hash)
+            .and_then(|&earlier| self.recorded_at(earlier))
+            .filter(|earlier| earlier.key.cells == key.cells)
+            .map(|earlier| {
+                let period = generation - earlier.generation;
+                let (dy, dx) = (
+                    key.corner.0 - earlier.key.corner.0,
+                    key.corner.1 - earlier.key.corner.1,
+                );
+                match (period, dy, dx) {
+                    (1, 0, 0) => Periodicity::Stable,
+                    (period, 0, 0) => Periodicity::Oscillator { period },
+                    (period, dy, dx) => Periodicity::Spaceship { period, dx, dy },
+                }
+            });
+
+        self.seen.insert(hash, generation);
+        self.stored_words += key.cells.len();
+        self.recorded.push_back(Recorded {
+            generation,
+            hash,
+            key,
+        });
+        while self.recorded.len() > 1
+            && (self.recorded.len() > MAX_PERIOD || self.stored_words > MAX_STORED_WORDS)
+        {
+            let oldest = self.recorded.pop_front().expect("Recorded is not empty");
+            self.stored_words -= oldest.key.cells.len();
+            if self.seen.get(&oldest.hash) == Some(&oldest.generation) {
+                self.seen.remove(&oldest.hash);
+            }
+        }
+    }
+
+    fn recorded_at(&self, generation: u64) -> Option<&Recorded> {
+        let index = self
+            .recorded
+            .binary_search_by_key(&generation, |recorded| recorded.generation)
+            .ok()?;
+        self.recorded.get(index)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.recorded.is_empty()
+    }
+
+    /// Copy which only knows the last found repetition, for showing it without
+    /// copying the remembered generations.
+    pub fn without_history(&self) -> Self {
+        Self {
+            found: self.found,
+            ..Self::default()
+        }
+    }
+
+    /// Forgets all generations, for example after cells were changed by hand.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Repetition which the last recorded generation completed.
+    pub fn found(&self) -> Option<Periodicity> {
+        self.found
+    }
+}
+
+/// Appends bytes like states of cells packed eight per word.
+pub fn pack_bytes(bytes: &[u8], output: &mut Vec<u64>) {
+    output.extend(bytes.chunks(BYTES_PER_WORD).map(|chunk| {
+        let mut word = [0; BYTES_PER_WORD];
+        word[..chunk.len()].copy_from_slice(chunk);
+        u64::from_le_bytes(word)
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(corner: (i64, i64), cells: &[u64]) -> PatternKey {
+        PatternKey {
+            corner,
+            cells: cells.to_vec(),
+        }
+    }
+
+    #[test]
+    fn reports_only_generations_with_equal_cells() {
+        let mut detector = PeriodDetector::default();
+        detector.record(0, key((0, 0), &[2, 2, 0b11, 0b01]));
+        detector.record(1, key((0, 0), &[2, 2, 0b11, 0b10]));
+        assert_eq!(detector.found(), None);
+        detector.record(2, key((3, 5), &[2, 2, 0b11, 0b01]));
+        let spaceship = Periodicity::Spaceship {
+            period: 2,
+            dx: 5,
+            dy: 3,
+        };
+        assert_eq!(detector.found(), Some(spaceship));
+    }
+
+    #[test]
+    fn forgets_generations_beyond_the_stored_words() {
+        let mut detector = PeriodDetector::default();
+        let large = vec![1; MAX_STORED_WORDS / 2 + 1];
+        detector.record(0, key((0, 0), &large));
+        detector.record(1, key((0, 0), &[1]));
+        detector.record(2, key((0, 0), &large));
+        assert!(detector.stored_words <= MAX_STORED_WORDS);
+        assert_eq!(
+            detector.recorded_at(0).map(|recorded| recorded.generation),
+            None
+        );
+        assert!(detector.recorded_at(1).is_some());
+    }
+
+    #[test]
+    fn copies_without_history_keep_the_found_repetition() {
+        let mut detector = PeriodDetector::default();
+        detector.record(0, key((0, 0), &[1, 1, 1]));
+        detector.record(1, key((0, 0), &[1, 1, 1]));
+        let copy = detector.without_history();
+        assert!(copy.is_empty());
+        assert_eq!(copy.found(), Some(Periodicity::Stable));
+    }
+
+    #[test]
+    fn packs_bytes_eight_per_word() {
+        let mut output = Vec::new();
+        pack_bytes(&[1, 2, 3, 4, 5, 6, 7, 8, 9], &mut output);
+        assert_eq!(output, vec![0x0807_0605_0403_0201, 9]);
+    }
+}
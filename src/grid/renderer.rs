@@ -0,0 +1,240 @@
+use std::ops::Range;
+
+use eframe::{
+    egui::Ui,
+    epaint::{vec2, Color32, Mesh, Pos2, Rect, Shape},
+};
+
+use super::{cell_ages::TRAIL_LENGTH, Colouring, Grid, GridTransform, LifeCell};
+
+const STROKE_WIDTH: f32 = 1.;
+/// Below this size in pixels grid lines would cover the cells completely.
+const MIN_CELL_SIZE_FOR_LINES: f32 = 4.;
+/// Smaller hexagons look like squares, so they are drawn like them.
+const MIN_HEXAGON_SIZE: f32 = 3.;
+/// Corners of a hexagon as wide as a cell relative to its centre in cell sizes.
+/// The tips reach a third of a cell into the rows above and below, so that shifted rows fit.
+const HEXAGON_CORNERS: [(f32, f32); 6] = [
+    (0., -2. / 3.),
+    (0.5, -1. / 3.),
+    (0.5, 1. / 3.),
+    (0., 2. / 3.),
+    (-0.5, 1. / 3.),
+    (-0.5, -1. / 3.),
+];
+
+struct VisibleCells {
+    rows: Range<usize>,
+    columns: Range<usize>,
+}
+
+impl Grid {
+    /// Draws only the cells inside the clip rect of the ui as one mesh.
+    /// Neighbouring cells of the same colour in a row are merged into one rectangle.
+    /// If cells are smaller than a pixel, only one cell per pixel is sampled.
+    pub fn draw_at(&self, ui: &mut Ui, transform: GridTransform) {
+        let Some(VisibleCells { rows, columns }) = self.visible_cells(transform, ui.clip_rect())
+        else {
+            return;
+        };
+        if self.rule.is_hexagonal() && transform.cell_size >= MIN_HEXAGON_SIZE {
+            self.draw_hexagons(ui, transform, VisibleCells { rows, columns });
+            return;
+        }
+
+        let mut mesh = Mesh::default();
+        let background = self.drawing.color_for(LifeCell::Dead);
+        let area = Rect::from_min_max(
+            transform.cell_min(rows.start as i64, columns.start as i64),
+            transform.cell_min(rows.end as i64, columns.end as i64),
+        );
+        mesh.add_colored_rect(area, background);
+
+        let step = (1. / transform.cell_size).ceil().max(1.) as usize;
+        for y in rows.clone().step_by(step) {
+            let y_end = (y + step).min(rows.end);
+            let mut run: Option<(usize, Color32)> = None;
+
+            for x in columns.clone().step_by(step) {
+                let color = self.color_at(y, x);
+                match run {
+                    Some((_, run_color)) if run_color == color => (),
+                    Some((run_start, run_color)) => {
+                        add_run(
+                            &mut mesh,
+                            transform,
+                            background,
+                            (y, y_end),
+                            (run_start, x),
+                            run_color,
+                        );
+                        run = Some((x, color));
+                    }
+                    None => run = Some((x, color)),
+                }
+            }
+
+            if let Some((run_start, run_color)) = run {
+                add_run(
+                    &mut mesh,
+                    transform,
+                    background,
+                    (y, y_end),
+                    (run_start, columns.end),
+                    run_color,
+                );
+            }
+        }
+
+        let lines = (
+            rows.start as i64..rows.end as i64,
+            columns.start as i64..columns.end as i64,
+        );
+        add_grid_lines(&mut mesh, transform, lines, self.drawing.stroke_color);
+
+        ui.painter().add(Shape::mesh(mesh));
+
+        fn add_run(
+            mesh: &mut Mesh,
+            transform: GridTransform,
+            background: Color32,
+            (y_start, y_end): (usize, usize),
+            (x_start, x_end): (usize, usize),
+            color: Color32,
+        ) {
+            if color != background {
+                let rect = Rect::from_min_max(
+                    transform.cell_min(y_start as i64, x_start as i64),
+                    transform.cell_min(y_end as i64, x_end as i64),
+                );
+                mesh.add_colored_rect(rect, color);
+            }
+        }
+    }
+
+    /// Draws every visible cell as a hexagon with odd rows shifted half a cell to the right.
+    /// Large hexagons are drawn a little smaller, so that the lines between them show through.
+    fn draw_hexagons(&self, ui: &mut Ui, transform: GridTransform, visible: VisibleCells) {
+        let VisibleCells { rows, columns } = visible;
+        // Shifted cells left of the first visible column reach into it.
+        let columns = columns.start.saturating_sub(1)..columns.end;
+        let size = transform.cell_size;
+        let with_lines = size >= MIN_CELL_SIZE_FOR_LINES;
+        let background = self.drawing.color_for(LifeCell::Dead);
+
+        let mut mesh = Mesh::default();
+        let area = Rect::from_min_max(
+            transform.cell_min(rows.start as i64, columns.start as i64) - vec2(0., size / 6.),
+            transform.cell_min(rows.end as i64, columns.end as i64) + vec2(size / 2., size / 6.),
+        );
+        let area_color = if with_lines {
+            self.drawing.stroke_color
+        } else {
+            background
+        };
+        mesh.add_colored_rect(area, area_color);
+
+        let scale = if with_lines {
+            size - STROKE_WIDTH
+        } else {
+            size
+        };
+        for y in rows {
+            for x in columns.clone() {
+                let color = self.color_at(y, x);
+                if color == area_color {
+                    continue;
+                }
+                let centre = hexagon_centre(transform, y as i64, x as i64);
+                add_hexagon(&mut mesh, centre, scale, color);
+            }
+        }
+
+        ui.painter().add(Shape::mesh(mesh));
+    }
+
+    /// Colour of the cell itself or of its age or trail if the colouring shows them.
+    pub(super) fn color_at(&self, y: usize, x: usize) -> Color32 {
+        let cell = self.cell_at_y_x(y, x);
+        if let Some(ages) = &self.ages {
+            match (self.drawing.colouring, cell) {
+                (Colouring::Age, LifeCell::Alive) => {
+                    return self.drawing.age_color(ages.age(y, x));
+                }
+                (Colouring::Trails, LifeCell::Dead) if ages.since_death(y, x) > 0 => {
+                    return self
+                        .drawing
+                        .trail_color_for(ages.since_death(y, x), TRAIL_LENGTH);
+                }
+                _ => (),
+            }
+        }
+        self.drawing.color_for(cell)
+    }
+
+    fn visible_cells(&self, transform: GridTransform, clip: Rect) -> Option<VisibleCells> {
+        let (min_y, min_x) = transform.grid_position(clip.min);
+        let (max_y, max_x) = transform.grid_position(clip.max);
+        let clamp = |value: f32, limit: usize| value.clamp(0., limit as f32) as usize;
+
+        let rows = clamp(min_y.floor(), self.height)..clamp(max_y.ceil(), self.height);
+        let columns = clamp(min_x.floor(), self.width)..clamp(max_x.ceil(), self.width);
+        if rows.is_empty() || columns.is_empty() {
+            None
+        } else {
+            Some(VisibleCells { rows, columns })
+        }
+    }
+}
+
+/// Centre of the hexagon of a cell on a hexagonal grid, whose odd rows are shifted half a cell.
+fn hexagon_centre(transform: GridTransform, y: i64, x: i64) -> Pos2 {
+    let shift = if y.rem_euclid(2) == 1 { 0.5 } else { 0. };
+    transform.cell_min(y, x) + vec2(0.5 + shift, 0.5) * transform.cell_size
+}
+
+/// Adds a hexagon which is `size` wide as a fan of triangles around its centre.
+fn add_hexagon(mesh: &mut Mesh, centre: Pos2, size: f32, color: Color32) {
+    let first = mesh.vertices.len() as u32;
+    mesh.colored_vertex(centre, color);
+    for (x, y) in HEXAGON_CORNERS {
+        mesh.colored_vertex(centre + vec2(x, y) * size, color);
+    }
+    for corner in 0..6 {
+        mesh.add_triangle(first, first + 1 + corner, first + 1 + (corner + 1) % 6);
+    }
+}
+
+/// Adds lines around the given rows and columns if cells are large enough.
+pub fn add_grid_lines(
+    mesh: &mut Mesh,
+    transform: GridTransform,
+    (rows, columns): (Range<i64>, Range<i64>),
+    color: Color32,
+) {
+    if transform.cell_size < MIN_CELL_SIZE_FOR_LINES {
+        return;
+    }
+
+    let half_stroke = STROKE_WIDTH / 2.;
+    let area = Rect::from_min_max(
+        transform.cell_min(rows.start, columns.start),
+        transform.cell_min(rows.end, columns.end),
+    );
+    for y in rows.start..=rows.end {
+        let line_y = transform.cell_min(y, 0).y;
+        let line = Rect::from_min_max(
+            Pos2::new(area.min.x, line_y - half_stroke),
+            Pos2::new(area.max.x, line_y + half_stroke),
+        );
+        mesh.add_colored_rect(line, color);
+    }
+    for x in columns.start..=columns.end {
+        let line_x = transform.cell_min(0, x).x;
+        let line = Rect::from_min_max(
+            Pos2::new(line_x - half_stroke, area.min.y),
+            Pos2::new(line_x + half_stroke, area.max.y),
+        );
+        mesh.add_colored_rect(line, color);
+    }
+}
@@ -0,0 +1,437 @@
+use std::{fmt::Display, str::FromStr};
+
+use thiserror::Error;
+
+use super::LifeCell;
+
+mod larger_than_life;
+mod neighbourhoods;
+
+pub use larger_than_life::{LargerThanLife, NeighbourRows};
+use neighbourhoods::Neighbourhoods;
+
+const MAX_NEIGHBOURS: u32 = 8;
+const HEXAGONAL_NEIGHBOURS: u32 = 6;
+/// Alive and dead without any dying states in between.
+const TWO_STATES: u8 = 2;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum InvalidRule {
+    #[error("Rule must have the form B<digits>/S<digits> with an optional /C<states>, for example B3/S23 or B2/S/C3")]
+    MissingParts,
+    #[error("Rule part {0} must start with B, S or C")]
+    UnknownPart(String),
+    #[error("Rule has more than one {0} part")]
+    DuplicatePart(char),
+    #[error("Char {0} in rule is not a neighbour count between 0 and 8")]
+    InvalidCount(char),
+    #[error("Letter {letter} in rule is no arrangement of {count} neighbours")]
+    InvalidLetter { count: u32, letter: char },
+    #[error("Neighbour count {0} in rule is followed by - without any letters")]
+    MissingLetters(u32),
+    #[error("Number of states {0} must be between 2 and 255")]
+    InvalidStates(String),
+    #[error("Larger than Life rule needs at least the parts R, M, S and B like R5,C0,M1,S34..58,B34..45,NM")]
+    MissingRangeParts,
+    #[error("Hexagonal rules have only 6 neighbours, not {0}")]
+    HexagonalCount(u32),
+    #[error("Hexagonal rules can only count neighbours and have no letters")]
+    HexagonalLetters,
+    #[error("Larger than Life rule part {0} must start with R, C, M, S, B or N")]
+    UnknownRangePart(String),
+    #[error("Radius {0} must be between 1 and {max}", max = larger_than_life::MAX_RADIUS)]
+    InvalidRadius(String),
+    #[error("Middle {0} must be 0 or 1")]
+    InvalidMiddle(String),
+    #[error("Counts {0} must be a range like 34..58")]
+    InvalidCountRange(String),
+    #[error("Neighbourhood {0} must be M, N, C or + for Moore, von Neumann, circular or cross")]
+    UnknownShape(String),
+}
+
+/// Birth and survival conditions of a life like cellular automaton.
+/// Written in B/S notation like B3/S23 for Conway's game of life.
+/// The older S/B notation like 23/3 is accepted too.
+///
+/// Counts may be followed by letters of Hensel's notation to only mean some arrangements
+/// of the neighbours like B2-a/S12 or B3ai/S23-k. Such rules are called non-totalistic.
+///
+/// Generations rules add the number of states as a third part like B2/S/C3 or /2/3
+/// for Brian's Brain. Alive cells which do not survive pass through the dying states
+/// before they are dead and can be born again.
+///
+/// Larger than Life rules like R5,C0,M1,S34..58,B34..45,NM count the neighbours
+/// in a larger radius instead of the eight around a cell.
+///
+/// Rules ending with H like B2/S34H are run on a hexagonal grid where every cell has six neighbours.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rule {
+    conditions: Conditions,
+    states: u8,
+    hexagonal: bool,
+}
+
+/// Which cells are neighbours and how many of them let a cell be born or survive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Conditions {
+    /// Arrangements of the eight cells around a cell.
+    Neighbours {
+        birth: Neighbourhoods,
+        survival: Neighbourhoods,
+    },
+    LargerThanLife(LargerThanLife),
+}
+
+/// Conditions of a totalistic rule by the number of alive neighbours.
+#[derive(Clone, Copy, Debug)]
+pub struct CountConditions {
+    pub birth: [bool; MAX_NEIGHBOURS as usize + 1],
+    pub survival: [bool; MAX_NEIGHBOURS as usize + 1],
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Self::conway()
+    }
+}
+
+impl Rule {
+    pub fn conway() -> Self {
+        let birth = Neighbourhoods::with_count(3);
+        let mut survival = Neighbourhoods::with_count(2);
+        survival.union(&birth);
+        Self {
+            conditions: Conditions::Neighbours { birth, survival },
+            states: TWO_STATES,
+            hexagonal: false,
+        }
+    }
+
+    /// Number of states including alive and dead.
+    pub fn states(&self) -> u8 {
+        self.states
+    }
+
+    /// True if cells pass through dying states.
+    pub fn is_generations(&self) -> bool {
+        self.states > TWO_STATES
+    }
+
+    /// True if the rule runs on a hexagonal grid with six neighbours.
+    pub fn is_hexagonal(&self) -> bool {
+        self.hexagonal
+    }
+
+    pub fn larger_than_life(&self) -> Option<LargerThanLife> {
+        match self.conditions {
+            Conditions::LargerThanLife(larger_than_life) => Some(larger_than_life),
+            Conditions::Neighbours { .. } => None,
+        }
+    }
+
+    /// True if the rule only depends on the number of alive neighbours.
+    pub fn is_totalistic(&self) -> bool {
+        match &self.conditions {
+            Conditions::Neighbours { birth, survival } => {
+                birth.is_totalistic() && survival.is_totalistic()
+            }
+            Conditions::LargerThanLife(_) => true,
+        }
+    }
+
+    /// Birth and survival by the number of alive neighbours, only complete for totalistic rules.
+    pub fn count_conditions(&self) -> CountConditions {
+        let birth = std::array::from_fn(|count| self.is_birth(count));
+        let survival = std::array::from_fn(|count| self.is_survival(count));
+        CountConditions { birth, survival }
+    }
+
+    /// True if a dead cell with that many alive neighbours is born in every arrangement.
+    pub fn is_birth(&self, alive_neighbours: usize) -> bool {
+        match &self.conditions {
+            Conditions::Neighbours { birth, .. } => {
+                birth.contains_all(&Neighbourhoods::with_count(alive_neighbours as u32))
+            }
+            Conditions::LargerThanLife(rule) => rule.is_birth(alive_neighbours as u32),
+        }
+    }
+
+    /// True if an alive cell with that many alive neighbours survives in every arrangement.
+    pub fn is_survival(&self, alive_neighbours: usize) -> bool {
+        match &self.conditions {
+            Conditions::Neighbours { survival, .. } => {
+                survival.contains_all(&Neighbourhoods::with_count(alive_neighbours as u32))
+            }
+            Conditions::LargerThanLife(rule) => rule.is_survival(alive_neighbours as u32),
+        }
+    }
+
+    /// The neighbourhood has a bit for every alive neighbour in the order of `NEIGHBOUR_OFFSETS`
+    /// or of `HEXAGONAL_NEIGHBOUR_OFFSETS` for hexagonal rules.
+    /// Larger than Life rules only see the eight neighbours here and count them.
+    pub fn next_cell(&self, cell: LifeCell, neighbourhood: u8) -> LifeCell {
+        let (born, survives) = match &self.conditions {
+            Conditions::Neighbours { birth, survival } => (
+                birth.contains(neighbourhood),
+                survival.contains(neighbourhood),
+            ),
+            Conditions::LargerThanLife(_) => {
+                let count = neighbourhood.count_ones() as usize;
+                (self.is_birth(count), self.is_survival(count))
+            }
+        };
+        match cell {
+            LifeCell::Alive if survives => LifeCell::Alive,
+            LifeCell::Alive => self.after_dying_stage(0),
+            LifeCell::Dead | LifeCell::State(_) if born => LifeCell::Alive,
+            LifeCell::Dead | LifeCell::State(_) => LifeCell::Dead,
+            LifeCell::Dying(stage) => self.after_dying_stage(stage),
+        }
+    }
+
+    /// Returns the state after the given dying stage, where stage 0 is an alive cell which dies.
+    pub fn after_dying_stage(&self, stage: u8) -> LifeCell {
+        if stage + 2 < self.states {
+            LifeCell::Dying(stage + 1)
+        } else {
+            LifeCell::Dead
+        }
+    }
+}
+
+impl FromStr for Rule {
+    type Err = InvalidRule;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim_start().starts_with(['R', 'r']) {
+            let (larger_than_life, states) = LargerThanLife::parse(s)?;
+            return Ok(Self {
+                conditions: Conditions::LargerThanLife(larger_than_life),
+                states,
+                hexagonal: false,
+            });
+        }
+
+        let trimmed = s.trim();
+        if let Some(square) = trimmed.strip_suffix(['H', 'h']) {
+            let rule: Rule = square.parse()?;
+            let Conditions::Neighbours { birth, survival } = &rule.conditions else {
+                unreachable!("Larger than Life rules start with R and not with B or S");
+            };
+            if !rule.is_totalistic() {
+                return Err(InvalidRule::HexagonalLetters);
+            }
+            for count in HEXAGONAL_NEIGHBOURS + 1..=MAX_NEIGHBOURS {
+                let with_count = Neighbourhoods::with_count(count);
+                if !birth.is_disjoint(&with_count) || !survival.is_disjoint(&with_count) {
+                    return Err(InvalidRule::HexagonalCount(count));
+                }
+            }
+            return Ok(Self {
+                hexagonal: true,
+                ..rule
+            });
+        }
+
+        let mut birth = None;
+        let mut survival = None;
+        let mut states = None;
+
+        let parts: Vec<&str> = s.trim().split('/').map(str::trim).collect();
+        if !(2..=3).contains(&parts.len()) {
+            return Err(InvalidRule::MissingParts);
+        }
+
+        // Older notation without letters: survival counts first, then birth counts like 23/3.
+        // Generations rules add the number of states like /2/3.
+        if parts
+            .iter()
+            .all(|part| part.chars().all(|c| c.is_ascii_digit()))
+        {
+            return Ok(Self {
+                conditions: Conditions::Neighbours {
+                    survival: parse_counts(parts[0])?,
+                    birth: parse_counts(parts[1])?,
+                },
+                states: match parts.get(2) {
+                    Some(states) => parse_states(states)?,
+                    None => TWO_STATES,
+                },
+                hexagonal: false,
+            });
+        }
+
+        for part in parts {
+            let mut chars = part.chars();
+            let kind = chars.next().map(|c| c.to_ascii_uppercase());
+            match kind {
+                Some('B') if birth.is_some() => return Err(InvalidRule::DuplicatePart('B')),
+                Some('S') if survival.is_some() => return Err(InvalidRule::DuplicatePart('S')),
+                Some('C') if states.is_some() => return Err(InvalidRule::DuplicatePart('C')),
+                Some('B') => birth = Some(parse_counts(chars.as_str())?),
+                Some('S') => survival = Some(parse_counts(chars.as_str())?),
+                Some('C') => states = Some(parse_states(chars.as_str())?),
+                _ => return Err(InvalidRule::UnknownPart(part.to_owned())),
+            }
+        }
+
+        return match (birth, survival) {
+            (Some(birth), Some(survival)) => Ok(Self {
+                conditions: Conditions::Neighbours { birth, survival },
+                states: states.unwrap_or(TWO_STATES),
+                hexagonal: false,
+            }),
+            _ => Err(InvalidRule::MissingParts),
+        };
+
+        fn parse_states(digits: &str) -> Result<u8, InvalidRule> {
+            match digits.parse::<u8>() {
+                Ok(states) if states >= TWO_STATES => Ok(states),
+                _ => Err(InvalidRule::InvalidStates(digits.to_owned())),
+            }
+        }
+
+        fn parse_counts(conditions: &str) -> Result<Neighbourhoods, InvalidRule> {
+            let mut parsed = Neighbourhoods::default();
+            let mut chars = conditions.chars().peekable();
+            while let Some(digit) = chars.next() {
+                let count = match digit.to_digit(10) {
+                    Some(count) if count <= MAX_NEIGHBOURS => count,
+                    _ => return Err(InvalidRule::InvalidCount(digit)),
+                };
+                let without = chars.next_if_eq(&'-').is_some();
+                let mut letters = Vec::new();
+                while let Some(letter) = chars.next_if(char::is_ascii_alphabetic) {
+                    letters.push(letter.to_ascii_lowercase());
+                }
+
+                let mut with_count = match (without, letters.is_empty()) {
+                    (true, true) => return Err(InvalidRule::MissingLetters(count)),
+                    (true, false) | (false, true) => Neighbourhoods::with_count(count),
+                    (false, false) => Neighbourhoods::default(),
+                };
+                for letter in letters {
+                    let arrangements = Neighbourhoods::hensel(count, letter)
+                        .ok_or(InvalidRule::InvalidLetter { count, letter })?;
+                    if without {
+                        with_count.remove_all(&arrangements);
+                    } else {
+                        with_count.union(&arrangements);
+                    }
+                }
+                parsed.union(&with_count);
+            }
+            Ok(parsed)
+        }
+    }
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (birth, survival) = match &self.conditions {
+            Conditions::Neighbours { birth, survival } => (birth, survival),
+            Conditions::LargerThanLife(larger_than_life) => {
+                return write!(f, "{}", larger_than_life.rule_string(self.states));
+            }
+        };
+        write!(
+            f,
+            "B{}/S{}",
+            conditions_to_string(birth),
+            conditions_to_string(survival)
+        )?;
+        if self.is_generations() {
+            write!(f, "/C{}", self.states)?;
+        }
+        if self.hexagonal {
+            write!(f, "H")?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes every count with either the letters which are included or the ones which are not,
+/// whatever is shorter.
+fn conditions_to_string(conditions: &Neighbourhoods) -> String {
+    let mut output = String::new();
+    for count in 0..=MAX_NEIGHBOURS {
+        let with_count = Neighbourhoods::with_count(count);
+        if conditions.is_disjoint(&with_count) {
+            continue;
+        }
+        output.push(char::from_digit(count, 10).unwrap());
+        if conditions.contains_all(&with_count) {
+            continue;
+        }
+
+        let (included, excluded): (String, String) = Neighbourhoods::hensel_letters(count)
+            .partition(|&letter| {
+                let arrangements = Neighbourhoods::hensel(count, letter).unwrap();
+                conditions.contains_all(&arrangements)
+            });
+        if excluded.len() < included.len() {
+            output.push('-');
+            output.push_str(&excluded);
+        } else {
+            output.push_str(&included);
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NW: u8 = 1 << 0;
+    const N: u8 = 1 << 1;
+    const NE: u8 = 1 << 2;
+    const W: u8 = 1 << 3;
+    const E: u8 = 1 << 4;
+    const SW: u8 = 1 << 5;
+    const S: u8 = 1 << 6;
+
+    fn rule(text: &str) -> Rule {
+        text.parse().expect("Rule is valid")
+    }
+
+    #[test]
+    fn non_totalistic_rules_follow_the_letters() {
+        let b2_a = rule("B2-a/S12");
+        assert_eq!(b2_a.next_cell(LifeCell::Dead, NW | N), LifeCell::Dead);
+        assert_eq!(b2_a.next_cell(LifeCell::Dead, N | S), LifeCell::Alive);
+        assert_eq!(b2_a.next_cell(LifeCell::Alive, SW), LifeCell::Alive);
+        assert_eq!(b2_a.next_cell(LifeCell::Alive, N | W | E), LifeCell::Dead);
+
+        let b3ai = rule("B3ai/S23-k");
+        assert_eq!(b3ai.next_cell(LifeCell::Dead, NW | N | W), LifeCell::Alive);
+        assert_eq!(b3ai.next_cell(LifeCell::Dead, NE | E | S), LifeCell::Dead);
+        assert_eq!(b3ai.next_cell(LifeCell::Alive, N | SW), LifeCell::Alive);
+        assert_eq!(
+            b3ai.next_cell(LifeCell::Alive, N | W | 1 << 7),
+            LifeCell::Dead
+        );
+        assert_eq!(b3ai.next_cell(LifeCell::Alive, NW | N | W), LifeCell::Alive);
+    }
+
+    #[test]
+    fn rule_strings_round_trip() {
+        let canonical = [
+            ("B3/S23", "B3/S23"),
+            ("23/3", "B3/S23"),
+            ("b3ia/s23-k", "B3ai/S23-k"),
+            ("B2-a/S12", "B2-a/S12"),
+            ("B3cekainyqjr/S2cekan", "B3/S2-i"),
+            ("/2/3", "B2/S/C3"),
+            ("B2/S34H", "B2/S34H"),
+            ("R5,C0,M1,S34..58,B34..45,NM", "R5,C0,M1,S34..58,B34..45,NM"),
+        ];
+        for (text, expected) in canonical {
+            let parsed = rule(text);
+            assert_eq!(parsed.to_string(), expected);
+            assert_eq!(rule(&parsed.to_string()), parsed);
+        }
+        assert!(rule("B3/S23").is_totalistic());
+        assert!(!rule("B3ai/S23-k").is_totalistic());
+    }
+}
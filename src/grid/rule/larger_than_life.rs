@@ -0,0 +1,213 @@
+use super::InvalidRule;
+
+/// Larger radii make the summed area tables bigger than most grids.
+pub const MAX_RADIUS: u32 = 500;
+
+/// Cells around a cell which count as its neighbours in Larger than Life rules.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NeighbourhoodShape {
+    /// Square with sides of twice the radius plus one.
+    Moore,
+    /// Diamond of the cells whose rows and columns differ by at most the radius together.
+    VonNeumann,
+    /// Cells whose centres are less than the radius plus a half away.
+    Circular,
+    /// Row and column through the cell.
+    Cross,
+}
+
+impl NeighbourhoodShape {
+    const ALL: [NeighbourhoodShape; 4] = [
+        NeighbourhoodShape::Moore,
+        NeighbourhoodShape::VonNeumann,
+        NeighbourhoodShape::Circular,
+        NeighbourhoodShape::Cross,
+    ];
+
+    /// Letter after the N in the rule.
+    fn letter(self) -> char {
+        match self {
+            Self::Moore => 'M',
+            Self::VonNeumann => 'N',
+            Self::Circular => 'C',
+            Self::Cross => '+',
+        }
+    }
+
+    /// Number of cells left and right of the middle column which belong to the row at `offset`.
+    fn half_width(self, radius: i64, offset: i64) -> i64 {
+        let offset = offset.abs();
+        match self {
+            Self::Moore => radius,
+            Self::VonNeumann => radius - offset,
+            Self::Circular => {
+                let squared = radius * radius + radius - offset * offset;
+                (squared as f64).sqrt().floor() as i64
+            }
+            Self::Cross if offset == 0 => radius,
+            Self::Cross => 0,
+        }
+    }
+}
+
+/// Rows of a neighbourhood next to each other with the same width, relative to the cell.
+#[derive(Clone, Copy, Debug)]
+pub struct NeighbourRows {
+    pub first_row: i64,
+    pub last_row: i64,
+    pub half_width: i64,
+}
+
+/// Conditions of a Larger than Life rule like R5,C0,M1,S34..58,B34..45,NM.
+/// The neighbours are all cells in the given shape and radius around a cell
+/// and its conditions are ranges of the number of alive neighbours.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LargerThanLife {
+    radius: u32,
+    shape: NeighbourhoodShape,
+    /// Whether the cell itself counts as one of its neighbours.
+    includes_middle: bool,
+    birth: (u32, u32),
+    survival: (u32, u32),
+}
+
+impl LargerThanLife {
+    pub fn radius(&self) -> u32 {
+        self.radius
+    }
+
+    pub fn includes_middle(&self) -> bool {
+        self.includes_middle
+    }
+
+    /// Writes the rule like R5,C0,M1,S34..58,B34..45,NM.
+    pub fn rule_string(self, states: u8) -> String {
+        let states = if states > super::TWO_STATES {
+            states
+        } else {
+            0
+        };
+        format!(
+            "R{},C{},M{},S{}..{},B{}..{},N{}",
+            self.radius,
+            states,
+            u8::from(self.includes_middle),
+            self.survival.0,
+            self.survival.1,
+            self.birth.0,
+            self.birth.1,
+            self.shape.letter()
+        )
+    }
+
+    pub fn is_birth(&self, alive_neighbours: u32) -> bool {
+        (self.birth.0..=self.birth.1).contains(&alive_neighbours)
+    }
+
+    pub fn is_survival(&self, alive_neighbours: u32) -> bool {
+        (self.survival.0..=self.survival.1).contains(&alive_neighbours)
+    }
+
+    /// Splits the neighbourhood including the middle into rectangles,
+    /// so that the cells in it can be counted with a few lookups in a summed area table.
+    pub fn neighbour_rows(&self) -> Vec<NeighbourRows> {
+        let radius = self.radius as i64;
+        let mut rows: Vec<NeighbourRows> = Vec::new();
+        for offset in -radius..=radius {
+            let half_width = self.shape.half_width(radius, offset);
+            match rows.last_mut() {
+                Some(last) if last.half_width == half_width && last.last_row + 1 == offset => {
+                    last.last_row = offset;
+                }
+                _ => rows.push(NeighbourRows {
+                    first_row: offset,
+                    last_row: offset,
+                    half_width,
+                }),
+            }
+        }
+        rows
+    }
+
+    /// Parses a rule like R5,C0,M1,S34..58,B34..45,NM, where the shape N is optional.
+    /// Returns the conditions and the number of states.
+    pub fn parse(rule: &str) -> Result<(Self, u8), InvalidRule> {
+        let mut radius = None;
+        let mut states = None;
+        let mut includes_middle = None;
+        let mut survival = None;
+        let mut birth = None;
+        let mut shape = None;
+
+        for part in rule.split(',').map(str::trim) {
+            let mut chars = part.chars();
+            let kind = chars.next().map(|c| c.to_ascii_uppercase());
+            let value = chars.as_str();
+            let duplicate = match kind {
+                Some('R') => radius.replace(parse_radius(value)?).is_some(),
+                Some('C') => states.replace(parse_states(value)?).is_some(),
+                Some('M') => includes_middle.replace(parse_middle(value)?).is_some(),
+                Some('S') => survival.replace(parse_counts(value)?).is_some(),
+                Some('B') => birth.replace(parse_counts(value)?).is_some(),
+                Some('N') => shape.replace(parse_shape(value)?).is_some(),
+                _ => return Err(InvalidRule::UnknownRangePart(part.to_owned())),
+            };
+            if duplicate {
+                return Err(InvalidRule::DuplicatePart(kind.unwrap()));
+            }
+        }
+
+        match (radius, includes_middle, survival, birth) {
+            (Some(radius), Some(includes_middle), Some(survival), Some(birth)) => Ok((
+                Self {
+                    radius,
+                    shape: shape.unwrap_or(NeighbourhoodShape::Moore),
+                    includes_middle,
+                    birth,
+                    survival,
+                },
+                states.unwrap_or(super::TWO_STATES),
+            )),
+            _ => Err(InvalidRule::MissingRangeParts),
+        }
+    }
+}
+
+fn parse_radius(digits: &str) -> Result<u32, InvalidRule> {
+    match digits.parse::<u32>() {
+        Ok(radius) if (1..=MAX_RADIUS).contains(&radius) => Ok(radius),
+        _ => Err(InvalidRule::InvalidRadius(digits.to_owned())),
+    }
+}
+
+/// C0 and C1 mean alive and dead only like C2.
+fn parse_states(digits: &str) -> Result<u8, InvalidRule> {
+    match digits.parse::<u8>() {
+        Ok(0 | 1) => Ok(super::TWO_STATES),
+        Ok(states) => Ok(states),
+        Err(_) => Err(InvalidRule::InvalidStates(digits.to_owned())),
+    }
+}
+
+fn parse_middle(digit: &str) -> Result<bool, InvalidRule> {
+    match digit {
+        "0" => Ok(false),
+        "1" => Ok(true),
+        _ => Err(InvalidRule::InvalidMiddle(digit.to_owned())),
+    }
+}
+
+fn parse_counts(counts: &str) -> Result<(u32, u32), InvalidRule> {
+    let invalid = || InvalidRule::InvalidCountRange(counts.to_owned());
+    let (min, max) = counts.split_once("..").ok_or_else(invalid)?;
+    let min = min.trim().parse::<u32>().map_err(|_| invalid())?;
+    let max = max.trim().parse::<u32>().map_err(|_| invalid())?;
+    Ok((min, max))
+}
+
+fn parse_shape(letter: &str) -> Result<NeighbourhoodShape, InvalidRule> {
+    NeighbourhoodShape::ALL
+        .into_iter()
+        .find(|shape| letter.eq_ignore_ascii_case(&shape.letter().to_string()))
+        .ok_or_else(|| InvalidRule::UnknownShape(letter.to_owned()))
+}
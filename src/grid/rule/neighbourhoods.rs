@@ -0,0 +1,233 @@
+use crate::grid::NEIGHBOUR_OFFSETS;
+
+const NW: u8 = 1 << 0;
+const N: u8 = 1 << 1;
+const NE: u8 = 1 << 2;
+const W: u8 = 1 << 3;
+const E: u8 = 1 << 4;
+const SW: u8 = 1 << 5;
+const S: u8 = 1 << 6;
+const SE: u8 = 1 << 7;
+
+/// One arrangement for every letter of Hensel's notation up to 4 alive neighbours.
+/// Each letter stands for all arrangements which are rotations or reflections of it.
+/// The letters for 5 to 8 neighbours are the same arrangements with dead and alive swapped.
+const HENSEL_LETTERS: [&[(char, u8)]; 5] = [
+    &[],
+    &[('c', NW), ('e', N)],
+    &[
+        ('c', NW | NE),
+        ('e', N | W),
+        ('k', N | SW),
+        ('a', NW | N),
+        ('i', N | S),
+        ('n', NW | SE),
+    ],
+    &[
+        ('c', NW | NE | SE),
+        ('e', N | W | E),
+        ('k', N | W | SE),
+        ('a', NW | N | W),
+        ('i', NW | W | SW),
+        ('n', NW | NE | W),
+        ('y', NW | NE | S),
+        ('q', NW | E | SE),
+        ('j', NE | E | S),
+        ('r', N | NE | S),
+    ],
+    &[
+        ('c', NW | NE | SW | SE),
+        ('e', N | W | E | S),
+        ('k', NW | NE | E | S),
+        ('a', NW | N | NE | E),
+        ('i', NW | NE | W | E),
+        ('n', NW | N | NE | SW),
+        ('y', NW | NE | SW | S),
+        ('q', NW | N | W | SE),
+        ('j', N | W | E | SW),
+        ('r', NW | N | W | E),
+        ('t', NW | N | NE | S),
+        ('w', NW | W | S | SE),
+        ('z', NW | N | S | SE),
+    ],
+];
+
+/// Arrangements for every number of alive neighbours.
+const WITH_COUNT: [Neighbourhoods; 9] = {
+    let mut sets = [Neighbourhoods([0; 4]); 9];
+    let mut arrangement: usize = 0;
+    while arrangement < 256 {
+        let count = (arrangement as u8).count_ones() as usize;
+        sets[count].0[arrangement / 64] |= 1 << (arrangement % 64);
+        arrangement += 1;
+    }
+    sets
+};
+
+/// Set of arrangements of alive neighbours with one bit for each of the 256 arrangements.
+/// Bit i of an arrangement is set if the neighbour at `NEIGHBOUR_OFFSETS[i]` is alive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct Neighbourhoods([u64; 4]);
+
+impl Neighbourhoods {
+    /// All arrangements with the given number of alive neighbours.
+    pub fn with_count(count: u32) -> Self {
+        WITH_COUNT[count as usize]
+    }
+
+    /// Arrangements of the letter in Hensel's notation for the given number of alive neighbours.
+    pub fn hensel(count: u32, letter: char) -> Option<Self> {
+        let (letters, swapped) = match count {
+            0..=4 => (HENSEL_LETTERS[count as usize], false),
+            5..=8 => (HENSEL_LETTERS[8 - count as usize], true),
+            _ => return None,
+        };
+        let &(_, arrangement) = letters.iter().find(|&&(known, _)| known == letter)?;
+        let arrangement = if swapped { !arrangement } else { arrangement };
+
+        let mut set = Self::default();
+        for symmetric in symmetries(arrangement) {
+            set.insert(symmetric);
+        }
+        Some(set)
+    }
+
+    /// Letters of Hensel's notation for the given number of alive neighbours.
+    pub fn hensel_letters(count: u32) -> impl Iterator<Item = char> {
+        let index = if count <= 4 { count } else { 8 - count.min(8) };
+        let letters = HENSEL_LETTERS[index as usize];
+        letters.iter().map(|&(letter, _)| letter)
+    }
+
+    pub fn contains(&self, arrangement: u8) -> bool {
+        (self.0[arrangement as usize / 64] >> (arrangement % 64)) & 1 == 1
+    }
+
+    /// True if every arrangement of the other set is in this one.
+    pub fn contains_all(&self, other: &Self) -> bool {
+        self.0
+            .iter()
+            .zip(other.0)
+            .all(|(&own, other)| own & other == other)
+    }
+
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        self.0
+            .iter()
+            .zip(other.0)
+            .all(|(&own, other)| own & other == 0)
+    }
+
+    pub fn insert(&mut self, arrangement: u8) {
+        self.0[arrangement as usize / 64] |= 1 << (arrangement % 64);
+    }
+
+    pub fn union(&mut self, other: &Self) {
+        for (own, other) in self.0.iter_mut().zip(other.0) {
+            *own |= other;
+        }
+    }
+
+    pub fn remove_all(&mut self, other: &Self) {
+        for (own, other) in self.0.iter_mut().zip(other.0) {
+            *own &= !other;
+        }
+    }
+
+    /// True if only the number of alive neighbours matters and not where they are.
+    pub fn is_totalistic(&self) -> bool {
+        (0..=8).all(|count| {
+            let with_count = Self::with_count(count);
+            self.contains_all(&with_count) || self.is_disjoint(&with_count)
+        })
+    }
+}
+
+/// Moves a neighbour offset by a rotation or reflection.
+type Transform = fn(isize, isize) -> (isize, isize);
+
+/// Returns the arrangement rotated and reflected in all 8 ways.
+fn symmetries(arrangement: u8) -> [u8; 8] {
+    let transforms: [Transform; 8] = [
+        |y, x| (y, x),
+        |y, x| (x, -y),
+        |y, x| (-y, -x),
+        |y, x| (-x, y),
+        |y, x| (y, -x),
+        |y, x| (-y, x),
+        |y, x| (x, y),
+        |y, x| (-x, -y),
+    ];
+    transforms.map(|transform| {
+        NEIGHBOUR_OFFSETS
+            .iter()
+            .enumerate()
+            .filter(|&(bit, _)| (arrangement >> bit) & 1 == 1)
+            .map(|(_, &(y, x))| {
+                let moved = transform(y, x);
+                let bit = NEIGHBOUR_OFFSETS
+                    .iter()
+                    .position(|&offset| offset == moved)
+                    .unwrap();
+                1 << bit
+            })
+            .fold(0, |moved, bit| moved | bit)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn size(set: &Neighbourhoods) -> u32 {
+        set.0.iter().map(|word| word.count_ones()).sum()
+    }
+
+    #[test]
+    fn letters_split_every_count_into_separate_arrangements() {
+        for count in 1..=7 {
+            let mut covered = Neighbourhoods::default();
+            for letter in Neighbourhoods::hensel_letters(count) {
+                let arrangements = Neighbourhoods::hensel(count, letter).unwrap();
+                assert!(covered.is_disjoint(&arrangements), "{}{}", count, letter);
+                assert!(Neighbourhoods::with_count(count).contains_all(&arrangements));
+                covered.union(&arrangements);
+            }
+            assert_eq!(covered, Neighbourhoods::with_count(count), "{}", count);
+        }
+        assert_eq!(Neighbourhoods::hensel(0, 'c'), None);
+        assert_eq!(Neighbourhoods::hensel(3, 't'), None);
+    }
+
+    #[test]
+    fn letters_match_the_shapes_of_hensels_notation() {
+        // Corners and edges are 4 arrangements each, opposite neighbours only 2
+        // and the four corners or the four edges just 1.
+        let sizes = [
+            (1, 'c', 4),
+            (1, 'e', 4),
+            (2, 'i', 2),
+            (2, 'n', 2),
+            (2, 'k', 8),
+            (4, 'c', 1),
+            (4, 'e', 1),
+            (7, 'c', 4),
+        ];
+        for (count, letter, expected) in sizes {
+            let arrangements = Neighbourhoods::hensel(count, letter).unwrap();
+            assert_eq!(size(&arrangements), expected, "{}{}", count, letter);
+        }
+
+        let two_a = Neighbourhoods::hensel(2, 'a').unwrap();
+        assert!(two_a.contains(N | NE));
+        assert!(two_a.contains(SE | S));
+        assert!(!two_a.contains(N | E));
+
+        // Letters for more than four neighbours swap alive and dead neighbours.
+        let five_a = Neighbourhoods::hensel(5, 'a').unwrap();
+        let three_a = Neighbourhoods::hensel(3, 'a').unwrap();
+        for arrangement in 0..=u8::MAX {
+            assert_eq!(five_a.contains(arrangement), three_a.contains(!arrangement));
+        }
+    }
+}
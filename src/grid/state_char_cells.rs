@@ -0,0 +1,65 @@
+use super::{
+    dead_alive_char_cells::get_only_first_char, Automaton, DeadAliveCharCell, InvalidCharCell,
+};
+
+/// Char for every state of a hand written automaton by the number of the state.
+pub struct StateCharCells {
+    chars: Vec<char>,
+}
+
+impl StateCharCells {
+    /// Takes one input for every state of the automaton in the order of their numbers.
+    pub fn new(automaton: &dyn Automaton, inputs: &[String]) -> Result<Self, InvalidCharCell> {
+        let states = automaton.state_names().len();
+        if inputs.len() != states {
+            return Err(InvalidCharCell::WrongStateCount {
+                given: inputs.len(),
+                states,
+            });
+        }
+        let mut chars: Vec<char> = Vec::with_capacity(inputs.len());
+        for (input, &name) in inputs.iter().zip(automaton.state_names()) {
+            let char = get_only_first_char(input).ok_or(InvalidCharCell::TooLongForState(name))?;
+            if chars.contains(&char) {
+                return Err(InvalidCharCell::DuplicateChar(char));
+            }
+            chars.push(char);
+        }
+        Ok(Self { chars })
+    }
+
+    pub fn chars(&self) -> &[char] {
+        &self.chars
+    }
+}
+
+impl From<DeadAliveCharCell> for StateCharCells {
+    fn from(dead_alive: DeadAliveCharCell) -> Self {
+        Self {
+            chars: vec![dead_alive.dead(), dead_alive.alive()],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::automaton::Wireworld;
+
+    fn inputs(chars: &[&str]) -> Vec<String> {
+        chars.iter().map(|&char| char.to_owned()).collect()
+    }
+
+    #[test]
+    fn needs_one_char_for_every_state() {
+        let chars = StateCharCells::new(&Wireworld, &inputs(&[".", "H", "t", "#"])).unwrap();
+        assert_eq!(chars.chars(), ['.', 'H', 't', '#']);
+
+        for given in [inputs(&[".", "H", "t"]), inputs(&[".", "H", "t", "#", "x"])] {
+            assert!(matches!(
+                StateCharCells::new(&Wireworld, &given),
+                Err(InvalidCharCell::WrongStateCount { states: 4, .. })
+            ));
+        }
+    }
+}
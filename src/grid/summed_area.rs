@@ -0,0 +1,127 @@
+use super::{bit_board::BitBoard, Boundary, NeighbourRows};
+
+/// Number of alive cells in every rectangle from the top left corner,
+/// so that the alive cells of any rectangle can be counted with four lookups.
+/// The grid is padded by the radius on every side with the cells the boundary maps there.
+pub struct SummedArea {
+    radius: usize,
+    /// Width of a row of the table, one more than the padded grid.
+    stride: usize,
+    sums: Vec<u32>,
+}
+
+impl SummedArea {
+    pub fn new(
+        cells: &BitBoard,
+        boundary: Boundary,
+        (height, width): (usize, usize),
+        radius: usize,
+    ) -> Self {
+        let stride = width + 2 * radius + 1;
+        let rows = height + 2 * radius + 1;
+        let mut sums = vec![0; rows * stride];
+
+        for padded_y in 1..rows {
+            let y = padded_y as isize - 1 - radius as isize;
+            let mut row_sum = 0;
+            for padded_x in 1..stride {
+                let x = padded_x as isize - 1 - radius as isize;
+                let alive = boundary
+                    .resolve(height, width, y, x)
+                    .is_some_and(|(y, x)| cells.get(y, x));
+                row_sum += u32::from(alive);
+                sums[padded_y * stride + padded_x] =
+                    sums[(padded_y - 1) * stride + padded_x] + row_sum;
+            }
+        }
+
+        Self {
+            radius,
+            stride,
+            sums,
+        }
+    }
+
+    /// Counts the alive cells in the rows around the cell at the given row and column.
+    pub fn count(&self, y: usize, x: usize, neighbour_rows: &[NeighbourRows]) -> u32 {
+        // Rows and columns of the table are shifted by the padding and the empty first row.
+        let (y, x) = ((y + self.radius) as i64, (x + self.radius) as i64);
+        neighbour_rows
+            .iter()
+            .map(|rows| {
+                let top = (y + rows.first_row) as usize;
+                let bottom = (y + rows.last_row + 1) as usize;
+                let left = (x - rows.half_width) as usize;
+                let right = (x + rows.half_width + 1) as usize;
+                let sum = |y: usize, x: usize| self.sums[y * self.stride + x];
+                (sum(bottom, right) + sum(top, left)) - (sum(top, right) + sum(bottom, left))
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::LargerThanLife;
+
+    /// Whether the cell at the offset belongs to the neighbourhood, as the shapes are defined.
+    fn in_shape(shape: char, radius: i64, dy: i64, dx: i64) -> bool {
+        let inside = dy.abs() <= radius && dx.abs() <= radius;
+        inside
+            && match shape {
+                'M' => true,
+                'N' => dy.abs() + dx.abs() <= radius,
+                // Centres less than the radius plus a half away.
+                'C' => dy * dy + dx * dx <= radius * radius + radius,
+                '+' => dy == 0 || dx == 0,
+                _ => unreachable!(),
+            }
+    }
+
+    #[test]
+    fn counts_like_a_cell_by_cell_count() {
+        let (height, width) = (13, 17);
+        let mut cells = BitBoard::new(height, width);
+        let mut state = 0x3c6e_f372_fe94_f82b_u64;
+        for (y, x) in super::super::all_coords(height, width) {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            cells.set(y, x, state.is_multiple_of(3));
+        }
+
+        for boundary in [Boundary::Plane, Boundary::Torus] {
+            for radius in 1..=6 {
+                let table = SummedArea::new(&cells, boundary, (height, width), radius);
+                for shape in ['M', 'N', 'C', '+'] {
+                    let rule = format!("R{},C0,M1,S0..0,B1..1,N{}", radius, shape);
+                    let (larger_than_life, _) = LargerThanLife::parse(&rule).unwrap();
+                    let neighbour_rows = larger_than_life.neighbour_rows();
+                    let radius = radius as i64;
+
+                    for (y, x) in super::super::all_coords(height, width) {
+                        let expected = (-radius..=radius)
+                            .flat_map(|dy| (-radius..=radius).map(move |dx| (dy, dx)))
+                            .filter(|&(dy, dx)| in_shape(shape, radius, dy, dx))
+                            .filter_map(|(dy, dx)| {
+                                let (y, x) = (y as isize + dy as isize, x as isize + dx as isize);
+                                boundary.resolve(height, width, y, x)
+                            })
+                            .filter(|&(y, x)| cells.get(y, x))
+                            .count() as u32;
+                        assert_eq!(
+                            table.count(y, x, &neighbour_rows),
+                            expected,
+                            "{} on {} at {} {}",
+                            rule,
+                            boundary,
+                            y,
+                            x
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,64 +1,251 @@
-use std::{io::ErrorKind, path::Path};
+use std::{ffi::OsStr, fmt::Display, io::ErrorKind, iter::repeat_n, path::Path};
 
-use crate::grid::text_load_error::TextLoadError;
+use crate::grid::text_load_error::{SourceLocation, TextLoadError};
 
-use super::{dead_alive_char_cells::DeadAliveCharCell, LifeCell};
+use super::{LifeCell, Rule, StateCharCells};
+
+mod cells;
+mod life_105;
+mod life_106;
+pub mod rle;
+
+/// Chars of the cells of patterns which are given as coordinates of alive cells.
+const DEAD_CHAR: char = '.';
+const ALIVE_CHAR: char = 'O';
+/// Larger patterns would take gigabytes of memory before they are even run.
+pub const MAX_PATTERN_CELLS: usize = 1 << 26;
+/// Lines of leniently read plain text files starting with these chars are left out.
+const PLAIN_TEXT_COMMENTS: [char; 2] = ['#', '!'];
+
+/// Formats a pattern file can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatternFormat {
+    /// One char per cell with the chars chosen in the open view.
+    PlainText,
+    Rle,
+    /// Plain text of the LifeWiki with `.` and `O` and comments starting with `!`.
+    Cells,
+    /// Blocks of `.` and `*` placed by `#P` lines.
+    Life105,
+    /// One pair of coordinates per alive cell.
+    Life106,
+}
+
+impl PatternFormat {
+    /// Detects the format by the extension of the file and otherwise by its first lines.
+    pub fn detect(path: &Path, text: &str) -> Self {
+        let extension = path
+            .extension()
+            .and_then(OsStr::to_str)
+            .map(str::to_ascii_lowercase);
+        let lines = || text.lines().map(str::trim);
+        let first_line = lines().next().unwrap_or_default();
+        match extension.as_deref() {
+            Some("rle") => return Self::Rle,
+            Some("cells") => return Self::Cells,
+            // Both versions of the Life format share the extensions.
+            Some("lif" | "life") if first_line.starts_with("#Life 1.06") => return Self::Life106,
+            Some("lif" | "life") => return Self::Life105,
+            _ => (),
+        }
+
+        if first_line.starts_with("#Life 1.05") {
+            Self::Life105
+        } else if first_line.starts_with("#Life 1.06") {
+            Self::Life106
+        } else if lines()
+            .find(|line| !line.starts_with('#'))
+            .is_some_and(|line| line.starts_with('x') && line.contains('='))
+        {
+            Self::Rle
+        } else if lines().any(|line| {
+            // Comments like #Pattern by someone do not place a block.
+            line.strip_prefix("#P")
+                .and_then(parse_coordinates)
+                .is_some()
+        }) {
+            Self::Life105
+        } else if first_line.starts_with('!')
+            && lines()
+                .filter(|line| !line.starts_with('!'))
+                .all(cells::is_row)
+        {
+            Self::Cells
+        } else {
+            Self::PlainText
+        }
+    }
+}
+
+impl Display for PatternFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::PlainText => write!(f, "Plain text"),
+            Self::Rle => write!(f, "RLE"),
+            Self::Cells => write!(f, "Plaintext .cells"),
+            Self::Life105 => write!(f, "Life 1.05"),
+            Self::Life106 => write!(f, "Life 1.06"),
+        }
+    }
+}
 
 pub struct TextData {
-    dead_char: char,
-    alive_char: char,
+    /// Char of every state by its number, dead and alive for life like rules.
+    state_chars: Vec<char>,
     width: usize,
     height: usize,
     text_date: Vec<char>,
+    rule: Option<Rule>,
 }
 
 impl TextData {
-    pub fn new(path: &Path, dead_alive: DeadAliveCharCell) -> Result<Self, TextLoadError> {
-        let text = match std::fs::read_to_string(path) {
-            Err(error) if error.kind() == ErrorKind::NotFound => {
-                Err(TextLoadError::NoFileFound(path.to_owned()))
-            }
-            Err(error) => Err(TextLoadError::IoError(error)),
-            Ok(content) => Ok(content),
-        }?;
-        let first_line = text.lines().next().expect("No line supplied").to_owned();
-        let width = first_line.len();
+    /// Reads a pattern file in any of the known formats.
+    /// Only plain text files use the given chars of the states, read leniently if asked to.
+    /// RLE files without a rule may use the given number of states.
+    pub fn load(
+        path: &Path,
+        state_chars: StateCharCells,
+        lenient: bool,
+        states: u8,
+    ) -> Result<Self, TextLoadError> {
+        let text = read_file(path)?;
+        match PatternFormat::detect(path, &text) {
+            PatternFormat::PlainText => Self::from_plain_text(&text, state_chars, lenient),
+            PatternFormat::Rle => rle::parse_with_states(&text, states),
+            PatternFormat::Cells => cells::parse(&text),
+            PatternFormat::Life105 => life_105::parse(&text),
+            PatternFormat::Life106 => life_106::parse(&text),
+        }
+    }
 
-        let height = text.lines().count();
-        let mut text_date = Vec::with_capacity(height * width);
+    /// Reads plain text with one char per cell.
+    /// Rows of hexagonal patterns are read as they are stored, every odd row being
+    /// half a cell further right than the rows around it.
+    ///
+    /// Leniently read files may have comment lines starting with `#` or `!`, whitespace
+    /// after the rows and blank lines at the end. Short rows are filled up with dead cells.
+    fn from_plain_text(
+        text: &str,
+        state_chars: StateCharCells,
+        lenient: bool,
+    ) -> Result<Self, TextLoadError> {
+        let state_chars = state_chars.chars().to_vec();
+        let rows = if lenient {
+            lenient_rows(text, &state_chars)
+        } else {
+            text.lines().enumerate().collect()
+        };
+        let width = if lenient {
+            rows.iter().map(|(_, row)| row.chars().count()).max()
+        } else {
+            rows.first().map(|(_, row)| row.chars().count())
+        };
+        let width = match width {
+            Some(0) | None => return Err(TextLoadError::EmptyFile),
+            Some(width) => width,
+        };
 
-        let (dead_char, alive_char) = (dead_alive.dead(), dead_alive.alive());
-        for next_line in text.lines() {
-            validate_row(next_line, width, dead_char, alive_char)?;
+        let height = rows.len();
+        let mut text_date = Vec::with_capacity(height * width);
+        for (index, next_line) in rows {
+            if !lenient {
+                validate_width(index, next_line, width)?;
+            }
+            validate_chars(index, next_line, &state_chars)?;
             let next_line: Vec<char> = next_line.chars().collect();
             text_date.extend_from_slice(&next_line);
+            text_date.extend(repeat_n(state_chars[0], width - next_line.len()));
         }
 
         return Ok(Self {
-            dead_char,
-            alive_char,
+            state_chars,
             width,
             height,
             text_date,
+            rule: None,
         });
 
-        fn validate_row(
+        /// Points at the first char which is missing or too many.
+        fn validate_width(index: usize, row: &str, expected: usize) -> Result<(), TextLoadError> {
+            let width = row.chars().count();
+            if width != expected {
+                let at = SourceLocation::at_char(index, row, width.min(expected));
+                Err(TextLoadError::UnEqualWidth {
+                    width,
+                    expected,
+                    at,
+                })
+            } else {
+                Ok(())
+            }
+        }
+
+        fn validate_chars(
+            index: usize,
             row: &str,
-            width: usize,
-            dead_char: char,
-            alive_char: char,
+            state_chars: &[char],
         ) -> Result<(), TextLoadError> {
-            if row.len() != width {
-                Err(TextLoadError::UnEqualWidth(width))
-            } else if let Some(invalid_char) = row
+            match row
                 .chars()
-                .find(|&sym| sym != dead_char && sym != alive_char)
+                .enumerate()
+                .find(|(_, sym)| !state_chars.contains(sym))
             {
-                Err(TextLoadError::NotValidCellChar(invalid_char))
-            } else {
-                Ok(())
+                Some((column, char)) => Err(TextLoadError::NotValidCellChar {
+                    char,
+                    at: SourceLocation::at_char(index, row, column),
+                }),
+                None => Ok(()),
+            }
+        }
+    }
+
+    /// Pattern cut to the bounding box of the alive cells at the given rows and columns.
+    /// The cell which makes the pattern larger than [`MAX_PATTERN_CELLS`] is reported.
+    fn from_alive_cells(
+        format: PatternFormat,
+        text: &str,
+        alive_cells: &[PlacedCell],
+        rule: Option<Rule>,
+    ) -> Result<Self, TextLoadError> {
+        let mut bounds: Option<(i64, i64, i64, i64)> = None;
+        for cell in alive_cells {
+            let (top, left, bottom, right) = match bounds {
+                Some((top, left, bottom, right)) => (
+                    top.min(cell.y),
+                    left.min(cell.x),
+                    bottom.max(cell.y),
+                    right.max(cell.x),
+                ),
+                None => (cell.y, cell.x, cell.y, cell.x),
+            };
+            if pattern_size((top, left), (bottom, right)).is_none() {
+                return Err(cell.too_many_cells(text));
             }
+            bounds = Some((top, left, bottom, right));
+        }
+        let Some((top, left, bottom, right)) = bounds else {
+            return Err(TextLoadError::NoCells(format));
+        };
+        let (height, width) =
+            pattern_size((top, left), (bottom, right)).expect("Size was checked for every cell");
+
+        let mut text_date = vec![DEAD_CHAR; height * width];
+        for cell in alive_cells {
+            let (y, x) = ((cell.y - top) as usize, (cell.x - left) as usize);
+            text_date[super::y_x_to_index(width, y, x)] = ALIVE_CHAR;
         }
+        Ok(Self {
+            state_chars: vec![DEAD_CHAR, ALIVE_CHAR],
+            width,
+            height,
+            text_date,
+            rule,
+        })
+    }
+
+    pub fn is_rle_file(path: &Path) -> bool {
+        path.extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("rle"))
     }
 
     pub fn width(&self) -> usize {
@@ -67,14 +254,209 @@ impl TextData {
     pub fn height(&self) -> usize {
         self.height
     }
+    /// Rule given by the file itself like in the header of a RLE file.
+    pub fn rule(&self) -> Option<Rule> {
+        self.rule
+    }
 
     pub fn cell_at_y_x(&self, y: usize, x: usize) -> LifeCell {
         let index = super::y_x_to_index(self.width, y, x);
         let char_cell = self.text_date.get(index).unwrap();
-        match *char_cell {
-            cell if self.dead_char == cell => LifeCell::Dead,
-            cell if self.alive_char == cell => LifeCell::Alive,
-            cell => unreachable!("char: {}", cell),
+        match self.state_chars.iter().position(|state| state == char_cell) {
+            Some(state) => LifeCell::from_state(state as u8),
+            None => unreachable!("char: {}", char_cell),
         }
     }
 }
+
+/// Rows of a plain text file without comment lines, whitespace at their end
+/// and blank lines at the end of the file. Chars of states are never left out.
+/// Every row is returned with the index of its line.
+fn lenient_rows<'a>(text: &'a str, state_chars: &[char]) -> Vec<(usize, &'a str)> {
+    let is_comment = |line: &str| {
+        line.starts_with(|first| {
+            PLAIN_TEXT_COMMENTS.contains(&first) && !state_chars.contains(&first)
+        })
+    };
+    let mut rows: Vec<(usize, &str)> = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !is_comment(line))
+        .map(|(index, line)| {
+            let row = line
+                .trim_end_matches(|end: char| end.is_whitespace() && !state_chars.contains(&end));
+            (index, row)
+        })
+        .collect();
+    while rows.last().is_some_and(|(_, row)| row.is_empty()) {
+        rows.pop();
+    }
+    rows
+}
+
+/// Alive cell of a pattern given by coordinates together with the place it was given at.
+struct PlacedCell {
+    y: i64,
+    x: i64,
+    line_index: usize,
+    char_index: usize,
+}
+
+impl PlacedCell {
+    fn too_many_cells(&self, text: &str) -> TextLoadError {
+        let line = text.lines().nth(self.line_index).unwrap_or_default();
+        TextLoadError::TooManyCells {
+            limit: MAX_PATTERN_CELLS,
+            at: SourceLocation::at_char(self.line_index, line, self.char_index),
+        }
+    }
+}
+
+/// Height and width of the rectangle between the corners
+/// or none if it would have more than [`MAX_PATTERN_CELLS`] cells.
+fn pattern_size((top, left): (i64, i64), (bottom, right): (i64, i64)) -> Option<(usize, usize)> {
+    let extent = |first: i64, last: i64| {
+        let extent = last.checked_sub(first)?.checked_add(1)?;
+        usize::try_from(extent).ok()
+    };
+    let (height, width) = (extent(top, bottom)?, extent(left, right)?);
+    height
+        .checked_mul(width)
+        .filter(|&cells| cells <= MAX_PATTERN_CELLS)?;
+    Some((height, width))
+}
+
+/// Column and row separated by whitespace like `-1 2`.
+fn parse_coordinates(text: &str) -> Option<(i64, i64)> {
+    let mut numbers = text.split_whitespace().map(str::parse::<i64>);
+    match (numbers.next(), numbers.next(), numbers.next()) {
+        (Some(Ok(x)), Some(Ok(y)), None) => Some((x, y)),
+        _ => None,
+    }
+}
+
+pub fn read_file(path: &Path) -> Result<String, TextLoadError> {
+    match std::fs::read_to_string(path) {
+        Err(error) if error.kind() == ErrorKind::NotFound => {
+            Err(TextLoadError::NoFileFound(path.to_owned()))
+        }
+        Err(error) => Err(TextLoadError::IoError(error)),
+        Ok(content) => Ok(content),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::DeadAliveCharCell;
+
+    fn location(result: Result<TextData, TextLoadError>) -> (usize, usize) {
+        let error = result.err().expect("Pattern is invalid");
+        let at = error.location().expect("Error has a location");
+        (at.line, at.column)
+    }
+
+    fn plain_text(text: &str, lenient: bool) -> Result<TextData, TextLoadError> {
+        let chars = DeadAliveCharCell::new(".", "O").unwrap().into();
+        TextData::from_plain_text(text, chars, lenient)
+    }
+
+    #[test]
+    fn detects_the_format_by_extension_and_content() {
+        let detect = |path: &str, text: &str| PatternFormat::detect(Path::new(path), text);
+        assert_eq!(
+            detect("glider.lif", "#Life 1.06\n0 0\n"),
+            PatternFormat::Life106
+        );
+        assert_eq!(
+            detect("glider.LIFE", "#D Glider\n.*\n"),
+            PatternFormat::Life105
+        );
+        assert_eq!(
+            detect("glider", "#N\n#P -1 -1\n.*\n"),
+            PatternFormat::Life105
+        );
+        assert_eq!(
+            detect("glider", "#Life 1.06\n0 0\n"),
+            PatternFormat::Life106
+        );
+        assert_eq!(
+            detect("glider", "#Pattern by someone\n.O\n"),
+            PatternFormat::PlainText
+        );
+        assert_eq!(
+            detect("glider", "#N Glider\n#P 1 1\nx = 3, y = 3\nbo$2bo$3o!\n"),
+            PatternFormat::Rle
+        );
+        assert_eq!(
+            detect("glider", "!Name: Glider\n.O\n"),
+            PatternFormat::Cells
+        );
+    }
+
+    #[test]
+    fn plain_text_reports_the_line_and_column() {
+        assert_eq!(location(plain_text("..O\n.O\n", false)), (2, 3));
+        assert_eq!(location(plain_text("..O\n.O.O\n", false)), (2, 4));
+        assert_eq!(location(plain_text("..O\n.X.\n", false)), (2, 2));
+        assert_eq!(location(plain_text("# comment\n.O\n.OX\n", true)), (3, 3));
+    }
+
+    #[test]
+    fn cells_report_the_line_and_column() {
+        assert_eq!(
+            location(cells::parse("!Name: Glider\n.O\n..O\nOO*\n")),
+            (4, 3)
+        );
+    }
+
+    #[test]
+    fn life_105_reports_the_line_and_column() {
+        assert_eq!(location(life_105::parse("#Life 1.05\n#P x 1\n")), (2, 4));
+        assert_eq!(location(life_105::parse("#Life 1.05\n#R  23/9\n")), (2, 5));
+        assert_eq!(location(life_105::parse("#P 0 0\n.*\n.*x\n")), (3, 3));
+    }
+
+    #[test]
+    fn life_106_reports_the_line_and_column() {
+        assert_eq!(
+            location(life_106::parse("#Life 1.06\n0 0\n  1 x\n")),
+            (3, 3)
+        );
+    }
+
+    #[test]
+    fn life_106_rejects_cells_which_overflow_the_bounding_box() {
+        let text = "#Life 1.06\n0 0\n-9223372036854775808 9223372036854775807\n";
+        let error = life_106::parse(text).err().expect("Pattern is too large");
+        assert!(matches!(error, TextLoadError::TooManyCells { .. }));
+        assert_eq!(
+            error.location().map(|at| (at.line, at.column)),
+            Some((3, 1))
+        );
+    }
+
+    #[test]
+    fn life_106_rejects_cells_far_apart() {
+        let text = "#Life 1.06\n0 0\n1 1\n  1000000 1000000\n";
+        let error = life_106::parse(text).err().expect("Pattern is too large");
+        assert!(matches!(error, TextLoadError::TooManyCells { .. }));
+        assert_eq!(
+            error.location().map(|at| (at.line, at.column)),
+            Some((4, 3))
+        );
+    }
+
+    #[test]
+    fn life_105_rejects_blocks_at_the_end_of_the_plane() {
+        let text = "#Life 1.05\n#P 9223372036854775807 0\n.**\n";
+        let error = life_105::parse(text)
+            .err()
+            .expect("Cell is outside of the plane");
+        assert!(matches!(error, TextLoadError::TooManyCells { .. }));
+        assert_eq!(
+            error.location().map(|at| (at.line, at.column)),
+            Some((3, 2))
+        );
+    }
+}
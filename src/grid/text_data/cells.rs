@@ -0,0 +1,67 @@
+use crate::grid::text_load_error::{SourceLocation, TextLoadError};
+
+use super::{PatternFormat, TextData};
+
+const CELLS_DEAD: char = '.';
+const CELLS_ALIVE: char = 'O';
+const CELLS_COMMENT: char = '!';
+
+/// True if the line only has the chars of dead and alive cells.
+pub fn is_row(line: &str) -> bool {
+    line.chars()
+        .all(|cell| cell == CELLS_DEAD || cell == CELLS_ALIVE)
+}
+
+/// Parses a pattern in the plaintext format of the LifeWiki.
+///
+/// ```text
+/// !Name: Glider
+/// .O
+/// ..O
+/// OOO
+/// ```
+///
+/// Rows may be shorter than the widest one, the missing cells at their end are dead.
+pub fn parse(text: &str) -> Result<TextData, TextLoadError> {
+    let mut rows: Vec<(usize, &str)> = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.starts_with(CELLS_COMMENT))
+        .map(|(index, line)| (index, line.trim_end()))
+        .collect();
+    while rows.last().is_some_and(|(_, row)| row.is_empty()) {
+        rows.pop();
+    }
+
+    let width = rows
+        .iter()
+        .map(|(_, row)| row.chars().count())
+        .max()
+        .unwrap_or(0);
+    let height = rows.len();
+    if width == 0 {
+        return Err(TextLoadError::NoCells(PatternFormat::Cells));
+    }
+
+    let mut text_date = vec![CELLS_DEAD; width * height];
+    for (y, (index, row)) in rows.into_iter().enumerate() {
+        for (x, cell) in row.chars().enumerate() {
+            match cell {
+                CELLS_DEAD => (),
+                CELLS_ALIVE => text_date[super::super::y_x_to_index(width, y, x)] = CELLS_ALIVE,
+                invalid => {
+                    let at = SourceLocation::at_char(index, row, x);
+                    return Err(TextLoadError::NotValidCellChar { char: invalid, at });
+                }
+            }
+        }
+    }
+
+    Ok(TextData {
+        state_chars: vec![CELLS_DEAD, CELLS_ALIVE],
+        width,
+        height,
+        text_date,
+        rule: None,
+    })
+}
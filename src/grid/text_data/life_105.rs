@@ -0,0 +1,79 @@
+use crate::grid::{
+    text_load_error::{indent_of, SourceLocation, TextLoadError},
+    Rule,
+};
+
+use super::{PatternFormat, PlacedCell, TextData, MAX_PATTERN_CELLS};
+
+const LIFE_105_DEAD: char = '.';
+const LIFE_105_ALIVE: char = '*';
+
+/// Parses a pattern in the Life 1.05 format.
+///
+/// ```text
+/// #Life 1.05
+/// #D Glider
+/// #N
+/// #P -1 -1
+/// .*
+/// ..*
+/// ***
+/// ```
+///
+/// Every `#P` line starts a block of rows whose top left cell is at the given column and row.
+/// The rule is given by `#N` for Conway's life or by `#R` with survival before birth like 23/3.
+pub fn parse(text: &str) -> Result<TextData, TextLoadError> {
+    let mut alive_cells = Vec::new();
+    let mut rule = None;
+    let (mut block_x, mut block_y) = (0, 0);
+    let mut row = 0;
+
+    for (index, line) in text.lines().map(str::trim_end).enumerate() {
+        if let Some(position) = line.strip_prefix("#P") {
+            (block_x, block_y) = super::parse_coordinates(position).ok_or_else(|| {
+                let at = SourceLocation::at_byte(index, line, "#P".len() + indent_of(position));
+                TextLoadError::InvalidLife105Block { at }
+            })?;
+            row = 0;
+        } else if let Some(rule_string) = line.strip_prefix("#R") {
+            let rule_start = "#R".len() + indent_of(rule_string);
+            let rule_string = rule_string.trim();
+            let parsed = rule_string.parse::<Rule>().map_err(|error| {
+                let at = SourceLocation::at_byte(index, line, rule_start);
+                TextLoadError::InvalidLife105Rule { error, at }
+            })?;
+            rule = Some(parsed);
+        } else if line.starts_with("#N") {
+            rule = Some(Rule::conway());
+        } else if !line.starts_with('#') {
+            for (column, cell) in line.chars().enumerate() {
+                match cell {
+                    LIFE_105_DEAD => (),
+                    LIFE_105_ALIVE => {
+                        let (Some(y), Some(x)) =
+                            (block_y.checked_add(row), block_x.checked_add(column as i64))
+                        else {
+                            return Err(TextLoadError::TooManyCells {
+                                limit: MAX_PATTERN_CELLS,
+                                at: SourceLocation::at_char(index, line, column),
+                            });
+                        };
+                        alive_cells.push(PlacedCell {
+                            y,
+                            x,
+                            line_index: index,
+                            char_index: column,
+                        });
+                    }
+                    invalid => {
+                        let at = SourceLocation::at_char(index, line, column);
+                        return Err(TextLoadError::NotValidCellChar { char: invalid, at });
+                    }
+                }
+            }
+            row += 1;
+        }
+    }
+
+    TextData::from_alive_cells(PatternFormat::Life105, text, &alive_cells, rule)
+}
@@ -0,0 +1,36 @@
+use crate::grid::text_load_error::{indent_of, SourceLocation, TextLoadError};
+
+use super::{PatternFormat, PlacedCell, TextData};
+
+/// Parses a pattern in the Life 1.06 format, one column and row of an alive cell per line.
+///
+/// ```text
+/// #Life 1.06
+/// 0 -1
+/// 1 0
+/// -1 1
+/// 0 1
+/// 1 1
+/// ```
+pub fn parse(text: &str) -> Result<TextData, TextLoadError> {
+    let mut alive_cells = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let indent = indent_of(line);
+        let (x, y) = super::parse_coordinates(trimmed).ok_or_else(|| {
+            let at = SourceLocation::at_byte(index, line, indent);
+            TextLoadError::InvalidLife106Line { at }
+        })?;
+        alive_cells.push(PlacedCell {
+            y,
+            x,
+            line_index: index,
+            char_index: line[..indent].chars().count(),
+        });
+    }
+
+    TextData::from_alive_cells(PatternFormat::Life106, text, &alive_cells, None)
+}
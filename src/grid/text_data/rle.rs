@@ -0,0 +1,543 @@
+use crate::grid::{
+    text_load_error::{indent_of, SourceLocation, TextLoadError},
+    Rule,
+};
+
+use super::{TextData, MAX_PATTERN_CELLS};
+
+const RLE_DEAD: char = 'b';
+const RLE_ALIVE: char = 'o';
+const RLE_END_OF_ROW: char = '$';
+const RLE_END: char = '!';
+const RLE_COMMENT: char = '#';
+const RLE_MAX_LINE_LENGTH: usize = 70;
+/// Patterns with more than two states are written like in Golly, with `.` for dead cells,
+/// `A` to `X` for the states 1 to 24 and a prefix from `p` to `y` for every further 24 states.
+const MULTI_STATE_DEAD: char = '.';
+const FIRST_STATE_LETTERS: [char; 2] = ['A', 'X'];
+const STATE_PREFIXES: [char; 2] = ['p', 'y'];
+const STATES_PER_PREFIX: u32 = 24;
+
+/// Parses a pattern in the run length encoded format.
+///
+/// ```text
+/// #N Glider
+/// x = 3, y = 3, rule = B3/S23
+/// bob$2bo$3o!
+/// ```
+///
+/// Patterns of generations rules may use the tokens of all states like `2A.B$CBA!`.
+///
+/// Patterns of hexagonal rules are written like in Golly, where the six neighbours of a cell
+/// are the eight around it without the top right and the bottom left one.
+/// They are turned into rows whose odd rows are shifted half a cell to the right.
+///
+/// Headers may leave out the rule, which is then chosen when the file is opened.
+/// Such patterns may only use the given number of states, others could not be shown.
+pub fn parse_with_states(text: &str, states_without_rule: u8) -> Result<TextData, TextLoadError> {
+    let mut lines = text.lines().enumerate().filter(|(_, line)| {
+        let line = line.trim();
+        !line.is_empty() && !line.starts_with(RLE_COMMENT)
+    });
+
+    let (header_index, header) = lines.next().ok_or(TextLoadError::MissingRleHeader)?;
+    let (width, height, rule) = parse_header(header_index, header)?;
+    let states = rule.map_or(states_without_rule, |rule| rule.states());
+
+    let mut runs = Runs {
+        states: vec![0; width * height],
+        width,
+        height,
+        position: (0, 0),
+    };
+    let mut run_count: Option<usize> = None;
+    let mut prefix: Option<char> = None;
+    let mut last_line = (header_index, header);
+    let mut reached_end = false;
+
+    'lines: for (index, line) in lines {
+        last_line = (index, line);
+        for (column, token) in line.chars().enumerate() {
+            let at = || SourceLocation::at_char(index, line, column);
+            let invalid_token = || TextLoadError::InvalidRleToken { token, at: at() };
+            if let Some(prefix) = prefix.take() {
+                let state = multi_state(Some(prefix), token).ok_or_else(invalid_token)?;
+                runs.place(run_count.take(), state, states, at)?;
+                continue;
+            }
+            match token {
+                digit if digit.is_ascii_digit() => {
+                    let digit = digit.to_digit(10).unwrap() as usize;
+                    // Too long runs saturate and are rejected as too wide or too many rows.
+                    let count = run_count.unwrap_or(0).saturating_mul(10);
+                    run_count = Some(count.saturating_add(digit));
+                }
+                RLE_DEAD | MULTI_STATE_DEAD => runs.place(run_count.take(), 0, states, at)?,
+                RLE_ALIVE => runs.place(run_count.take(), 1, states, at)?,
+                letter if multi_state(None, letter).is_some() => {
+                    let state = multi_state(None, letter).expect("Letter is a state");
+                    runs.place(run_count.take(), state, states, at)?;
+                }
+                prefix_letter
+                    if (STATE_PREFIXES[0]..=STATE_PREFIXES[1]).contains(&prefix_letter) =>
+                {
+                    prefix = Some(prefix_letter);
+                }
+                RLE_END_OF_ROW => {
+                    let (y, _) = runs.position;
+                    runs.position = (y.saturating_add(run_count.take().unwrap_or(1)), 0);
+                }
+                RLE_END => {
+                    reached_end = true;
+                    break 'lines;
+                }
+                whitespace if whitespace.is_whitespace() => (),
+                _ => return Err(invalid_token()),
+            }
+        }
+    }
+
+    if !reached_end {
+        let (index, line) = last_line;
+        let at = SourceLocation::after(index, line);
+        return Err(TextLoadError::MissingRleEnd { at });
+    }
+
+    let states = runs.states;
+    let (width, states) = match rule {
+        Some(rule) if rule.is_hexagonal() => unshear(width, height, &states),
+        _ => (width, states),
+    };
+    let last_state = states.iter().copied().max().unwrap_or(0).max(1);
+    Ok(TextData {
+        state_chars: (0..=last_state).map(char::from).collect(),
+        width,
+        height,
+        text_date: states.into_iter().map(char::from).collect(),
+        rule,
+    })
+}
+
+/// Pattern whose cells are only alive or dead if the header gives no rule.
+#[cfg(test)]
+pub fn parse(text: &str) -> Result<TextData, TextLoadError> {
+    parse_with_states(text, 2)
+}
+
+/// States of the cells of a RLE pattern together with the position of the next run.
+struct Runs {
+    states: Vec<u8>,
+    width: usize,
+    height: usize,
+    /// Row and column of the next cell.
+    position: (usize, usize),
+}
+
+impl Runs {
+    /// Puts a run of cells of the state at the current position.
+    fn place(
+        &mut self,
+        count: Option<usize>,
+        state: u8,
+        states: u8,
+        at: impl Fn() -> SourceLocation,
+    ) -> Result<(), TextLoadError> {
+        let count = count.unwrap_or(1);
+        let (y, x) = self.position;
+        if x.checked_add(count).is_none_or(|end| end > self.width) {
+            return Err(TextLoadError::RleRowTooWide {
+                row: y.saturating_add(1),
+                width: self.width,
+                at: at(),
+            });
+        }
+        if state >= states {
+            return Err(TextLoadError::RleStateOutOfRange {
+                state,
+                states,
+                at: at(),
+            });
+        }
+        if state != 0 {
+            if y >= self.height {
+                return Err(TextLoadError::RleTooManyRows {
+                    height: self.height,
+                    at: at(),
+                });
+            }
+            let start = super::super::y_x_to_index(self.width, y, x);
+            self.states[start..start + count].fill(state);
+        }
+        self.position = (y, x + count);
+        Ok(())
+    }
+}
+
+/// State of a letter of a multi state pattern, which may follow a prefix.
+fn multi_state(prefix: Option<char>, letter: char) -> Option<u8> {
+    let [first, last] = FIRST_STATE_LETTERS;
+    if !(first..=last).contains(&letter) {
+        return None;
+    }
+    let skipped = match prefix {
+        Some(prefix) => (prefix as u32 - STATE_PREFIXES[0] as u32 + 1) * STATES_PER_PREFIX,
+        None => 0,
+    };
+    u8::try_from(skipped + letter as u32 - first as u32 + 1).ok()
+}
+
+/// Token of a state of a multi state pattern, the inverse of [`multi_state`].
+fn multi_state_token(state: u8) -> String {
+    if state == 0 {
+        return MULTI_STATE_DEAD.to_string();
+    }
+    let (prefix, letter) = (
+        (state as u32 - 1) / STATES_PER_PREFIX,
+        (state as u32 - 1) % STATES_PER_PREFIX,
+    );
+    let letter = char::from_u32(FIRST_STATE_LETTERS[0] as u32 + letter).expect("Letter is valid");
+    match prefix {
+        0 => letter.to_string(),
+        prefix => {
+            let prefix =
+                char::from_u32(STATE_PREFIXES[0] as u32 + prefix - 1).expect("Prefix is valid");
+            format!("{}{}", prefix, letter)
+        }
+    }
+}
+
+/// Shifts every row so far left that the neighbours of the cells match the shifted odd rows.
+/// The grid gets wider by half its height, which keeps the first cell of the last row in place.
+fn unshear(width: usize, height: usize, sheared: &[u8]) -> (usize, Vec<u8>) {
+    let extra_columns = height / 2;
+    let unsheared_width = width + extra_columns;
+    let mut states = vec![0; unsheared_width * height];
+    for y in 0..height {
+        let shift = extra_columns - y.div_ceil(2);
+        let row = super::super::y_x_to_index(width, y, 0);
+        let unsheared_row = super::super::y_x_to_index(unsheared_width, y, shift);
+        states[unsheared_row..unsheared_row + width].copy_from_slice(&sheared[row..row + width]);
+    }
+    (unsheared_width, states)
+}
+
+fn parse_header(
+    line_index: usize,
+    header: &str,
+) -> Result<(usize, usize, Option<Rule>), TextLoadError> {
+    // Problems are reported at the byte of the header where the invalid part starts.
+    let at = |byte_index: usize| SourceLocation::at_byte(line_index, header, byte_index);
+    let invalid_header = |byte_index: usize| TextLoadError::InvalidRleHeader { at: at(byte_index) };
+
+    // The rule comes last and may contain commas itself like R5,C0,M1,S34..58,B34..45,NM.
+    let (sizes, rule_entry) = match header.find("rule") {
+        Some(start) => (
+            header[..start].trim_end().trim_end_matches(','),
+            Some((start, &header[start..])),
+        ),
+        None => (header, None),
+    };
+    let mut next_entry = 0;
+    let entries = sizes.split(',').map(|entry| {
+        let start = next_entry;
+        next_entry += entry.len() + ','.len_utf8();
+        (start, entry)
+    });
+
+    let (mut width, mut height, mut rule) = (None, None, None);
+    for (start, entry) in entries.chain(rule_entry) {
+        let entry_start = start + indent_of(entry);
+        let (key, value) = entry
+            .split_once('=')
+            .ok_or_else(|| invalid_header(entry_start))?;
+        let value_start = start + key.len() + '='.len_utf8() + indent_of(value);
+        let value = value.trim();
+        match key.trim() {
+            "x" => {
+                width = Some(
+                    value
+                        .parse::<usize>()
+                        .map_err(|_| invalid_header(value_start))?,
+                )
+            }
+            "y" => {
+                height = Some(
+                    value
+                        .parse::<usize>()
+                        .map_err(|_| invalid_header(value_start))?,
+                )
+            }
+            "rule" => {
+                let parsed =
+                    value
+                        .parse::<Rule>()
+                        .map_err(|error| TextLoadError::InvalidRleRule {
+                            error,
+                            at: at(value_start),
+                        })?;
+                rule = Some(parsed);
+            }
+            _ => return Err(invalid_header(entry_start)),
+        }
+    }
+
+    let header_start = indent_of(header);
+    let (Some(width), Some(height)) = (width, height) else {
+        return Err(invalid_header(header_start));
+    };
+    if width == 0 || height == 0 {
+        return Err(invalid_header(header_start));
+    }
+    // Hexagonal patterns get wider by half their height when they are unsheared.
+    let extra_columns = match rule {
+        Some(rule) if rule.is_hexagonal() => height / 2,
+        _ => 0,
+    };
+    let cells = width
+        .checked_add(extra_columns)
+        .and_then(|width| width.checked_mul(height));
+    if cells.is_none_or(|cells| cells > MAX_PATTERN_CELLS) {
+        return Err(TextLoadError::TooManyCells {
+            limit: MAX_PATTERN_CELLS,
+            at: at(header_start),
+        });
+    }
+    Ok((width, height, rule))
+}
+
+/// Writes a pattern in the run length encoded format.
+/// Generations rules write every state with the tokens of Golly, all others `b` and `o`.
+/// Dead cells at the end of a row and empty rows at the end are left out.
+pub fn encode(
+    width: usize,
+    height: usize,
+    rule: Rule,
+    state_at: impl Fn(usize, usize) -> u8,
+) -> String {
+    let token_of = |state: u8| {
+        if rule.is_generations() {
+            multi_state_token(state)
+        } else if state == 1 {
+            RLE_ALIVE.to_string()
+        } else {
+            RLE_DEAD.to_string()
+        }
+    };
+    let dead = token_of(0);
+    let mut tokens = Vec::new();
+    let mut pending_rows = 0;
+
+    for y in 0..height {
+        let mut runs: Vec<(usize, String)> = Vec::new();
+        for x in 0..width {
+            let token = token_of(state_at(y, x));
+            match runs.last_mut() {
+                Some((count, last)) if *last == token => *count += 1,
+                _ => runs.push((1, token)),
+            }
+        }
+        if runs.last().is_some_and(|(_, token)| *token == dead) {
+            runs.pop();
+        }
+
+        if !runs.is_empty() {
+            if pending_rows > 0 {
+                tokens.push(run_to_token(pending_rows, &RLE_END_OF_ROW.to_string()));
+                pending_rows = 0;
+            }
+            tokens.extend(
+                runs.into_iter()
+                    .map(|(count, token)| run_to_token(count, &token)),
+            );
+        }
+        pending_rows += 1;
+    }
+    tokens.push(RLE_END.to_string());
+
+    let mut output = format!("x = {}, y = {}, rule = {}\n", width, height, rule);
+    let mut line_length = 0;
+    for token in tokens {
+        if line_length + token.len() > RLE_MAX_LINE_LENGTH {
+            output.push('\n');
+            line_length = 0;
+        }
+        line_length += token.len();
+        output.push_str(&token);
+    }
+    output.push('\n');
+
+    return output;
+
+    fn run_to_token(count: usize, token: &str) -> String {
+        if count == 1 {
+            token.to_string()
+        } else {
+            format!("{}{}", count, token)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(text: &str) -> (usize, usize) {
+        let error = parse(text).err().expect("Pattern is invalid");
+        let at = error.location().expect("Error has a location");
+        (at.line, at.column)
+    }
+
+    #[test]
+    fn rejects_headers_larger_than_the_limit() {
+        let text = "x = 100000, y = 100000\no!\n";
+        assert!(matches!(
+            parse(text),
+            Err(TextLoadError::TooManyCells { .. })
+        ));
+        let text = "#C huge\nx = 18446744073709551615, y = 2\no!\n";
+        assert!(matches!(
+            parse(text),
+            Err(TextLoadError::TooManyCells { .. })
+        ));
+        assert_eq!(location(text), (2, 1));
+    }
+
+    #[test]
+    fn rejects_runs_which_overflow() {
+        let text = "x = 3, y = 3\n99999999999999999999999999o!\n";
+        assert!(matches!(
+            parse(text),
+            Err(TextLoadError::RleRowTooWide { .. })
+        ));
+        assert_eq!(location(text), (2, 27));
+
+        let text = "x = 3, y = 3\n99999999999999999999999999$o!\n";
+        assert!(matches!(
+            parse(text),
+            Err(TextLoadError::RleTooManyRows { .. })
+        ));
+    }
+
+    #[test]
+    fn multi_state_tokens_follow_golly() {
+        assert_eq!(multi_state(None, 'A'), Some(1));
+        assert_eq!(multi_state(None, 'X'), Some(24));
+        assert_eq!(multi_state(Some('p'), 'A'), Some(25));
+        assert_eq!(multi_state(Some('y'), 'O'), Some(255));
+        assert_eq!(multi_state(Some('y'), 'P'), None);
+        for state in 0..=u8::MAX {
+            let token = multi_state_token(state);
+            let mut chars = token.chars();
+            let parsed = match (chars.next(), chars.next()) {
+                (Some(MULTI_STATE_DEAD), None) => Some(0),
+                (Some(letter), None) => multi_state(None, letter),
+                (Some(prefix), Some(letter)) => multi_state(Some(prefix), letter),
+                _ => None,
+            };
+            assert_eq!(parsed, Some(state), "token {}", token);
+        }
+    }
+
+    #[test]
+    fn reads_the_states_of_generations_rules() {
+        let text = "x = 4, y = 2, rule = /2/4\n2A.B$CBA!\n";
+        let text_data = parse(text).expect("Pattern is valid");
+        let row = |y| {
+            (0..4)
+                .map(|x| text_data.cell_at_y_x(y, x).state())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(row(0), vec![1, 1, 0, 2]);
+        assert_eq!(row(1), vec![3, 2, 1, 0]);
+
+        let text = "x = 2, y = 1, rule = /2/4\nAD!\n";
+        assert!(matches!(
+            parse(text),
+            Err(TextLoadError::RleStateOutOfRange { state: 4, .. })
+        ));
+
+        // Without a rule only the states of the rule chosen when opening the file are known.
+        let text = "x = 3, y = 2\nA$2bB!\n";
+        assert!(matches!(
+            parse(text),
+            Err(TextLoadError::RleStateOutOfRange {
+                state: 2,
+                states: 2,
+                ..
+            })
+        ));
+        assert_eq!(location(text), (2, 5));
+        let wireworld = parse_with_states(text, 4).expect("Pattern is valid");
+        assert_eq!(wireworld.cell_at_y_x(1, 2).state(), 2);
+    }
+
+    #[test]
+    fn writes_the_states_of_generations_rules() {
+        let rule: Rule = "/2/3".parse().unwrap();
+        let states = [[1, 1, 0, 2], [0, 0, 0, 0], [2, 0, 0, 0]];
+        let encoded = encode(4, 3, rule, |y, x| states[y][x]);
+        assert_eq!(
+            encoded,
+            format!("x = 4, y = 3, rule = {}\n2A.B2$B!\n", rule)
+        );
+
+        let conway = Rule::conway();
+        let encoded = encode(4, 1, conway, |_, x| states[0][x]);
+        assert_eq!(encoded, format!("x = 4, y = 1, rule = {}\n2o!\n", conway));
+    }
+
+    #[test]
+    fn reports_the_line_and_column() {
+        assert_eq!(location("#N Glider\nx = 3 y = 3\nbo!\n"), (2, 5));
+        assert_eq!(location("x = 3, y = 3, rule = B3/S2x\nbo!\n"), (1, 22));
+        // Columns count chars, not bytes.
+        assert_eq!(location("x = 3,  y = é\nbo!\n"), (1, 13));
+        assert_eq!(location("x = é, zz = 1, y = 3\nbo!\n"), (1, 5));
+        assert_eq!(location("x = 3, zz = 1, y = 3\nbo!\n"), (1, 8));
+        assert_eq!(location("  x = 3\nbo!\n"), (1, 3));
+        assert_eq!(location("x = 3, y = 3\nbo$2bo$\n3oz!\n"), (3, 3));
+        assert_eq!(location("x = 3, y = 3\nbo$2bo$\n4o!\n"), (3, 2));
+        assert_eq!(location("x = 3, y = 1\nbo$\nbo!\n"), (3, 2));
+        assert_eq!(location("x = 3, y = 3\nbo$2bo$\n3o\n"), (3, 3));
+    }
+
+    #[test]
+    fn encoded_patterns_parse_to_the_same_states() {
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        for rule in ["B3/S23", "/2/3", "B2/S/C200"] {
+            let rule: Rule = rule.parse().unwrap();
+            let (width, height) = (90, 13);
+            let states: Vec<Vec<u8>> = (0..height)
+                .map(|_| {
+                    (0..width)
+                        .map(|_| {
+                            state ^= state << 13;
+                            state ^= state >> 7;
+                            state ^= state << 17;
+                            // Mostly dead cells so that empty rows and long runs appear.
+                            if state.is_multiple_of(4) {
+                                (state >> 8) as u8 % rule.states()
+                            } else {
+                                0
+                            }
+                        })
+                        .collect()
+                })
+                .collect();
+
+            let encoded = encode(width, height, rule, |y, x| states[y][x]);
+            assert!(encoded
+                .lines()
+                .all(|line| line.len() <= RLE_MAX_LINE_LENGTH));
+            let parsed = parse(&encoded).expect("Encoded pattern is valid");
+            assert_eq!((parsed.width(), parsed.height()), (width, height));
+            assert_eq!(parsed.rule(), Some(rule));
+            for (y, row) in states.iter().enumerate() {
+                let parsed_row: Vec<u8> = (0..width)
+                    .map(|x| parsed.cell_at_y_x(y, x).state())
+                    .collect();
+                assert_eq!(&parsed_row, row, "row {} of {}", y, rule);
+            }
+        }
+    }
+}
@@ -1,14 +1,152 @@
-use std::{io, path::PathBuf};
+use std::{fmt::Display, io, path::PathBuf};
 
 use thiserror::Error;
+
+use super::{InvalidRule, PatternFormat};
 #[derive(Debug, Error)]
 pub enum TextLoadError {
     #[error("File could not be loaded: {0}")]
     IoError(#[source] io::Error),
     #[error("There is no file at the path")]
     NoFileFound(PathBuf),
-    #[error("Char {0} is not a dead or an alive cell")]
-    NotValidCellChar(char),
-    #[error("Row at {0} has a width which not equal to the others")]
-    UnEqualWidth(usize),
+    #[error("File does not contain any cells")]
+    EmptyFile,
+    #[error("Char {char} at {at} is not the char of any cell state")]
+    NotValidCellChar { char: char, at: SourceLocation },
+    #[error("Row at {at} is {width} cells wide but the first row is {expected} cells wide")]
+    UnEqualWidth {
+        width: usize,
+        expected: usize,
+        at: SourceLocation,
+    },
+    #[error("RLE file has no header line like x = 3, y = 3")]
+    MissingRleHeader,
+    #[error("RLE header at {at} is invalid")]
+    InvalidRleHeader { at: SourceLocation },
+    #[error("Rule in RLE header at {at} is invalid: {error}")]
+    InvalidRleRule {
+        #[source]
+        error: InvalidRule,
+        at: SourceLocation,
+    },
+    #[error("Char {token} at {at} is not a valid RLE token")]
+    InvalidRleToken { token: char, at: SourceLocation },
+    #[error("RLE row {row} at {at} is wider than the width {width} given in the header")]
+    RleRowTooWide {
+        row: usize,
+        width: usize,
+        at: SourceLocation,
+    },
+    #[error("RLE pattern at {at} has more rows than the height {height} given in the header")]
+    RleTooManyRows { height: usize, at: SourceLocation },
+    #[error("State {state} at {at} does not exist in a rule with {states} states")]
+    RleStateOutOfRange {
+        state: u8,
+        states: u8,
+        at: SourceLocation,
+    },
+    #[error("RLE pattern is not terminated by ! at {at}")]
+    MissingRleEnd { at: SourceLocation },
+    #[error("Macrocell file does not start with [M2] at {at}")]
+    MissingMacrocellHeader { at: SourceLocation },
+    #[error("Rule in Macrocell file at {at} is invalid: {error}")]
+    InvalidMacrocellRule {
+        #[source]
+        error: InvalidRule,
+        at: SourceLocation,
+    },
+    #[error("Macrocell node at {at} is neither a square of cells nor made of earlier nodes")]
+    InvalidMacrocellNode { at: SourceLocation },
+    #[error("Macrocell file does not contain any nodes")]
+    EmptyMacrocell,
+    #[error("{0} file does not contain any cells")]
+    NoCells(PatternFormat),
+    #[error("Rule in Life 1.05 file at {at} is invalid: {error}")]
+    InvalidLife105Rule {
+        #[source]
+        error: InvalidRule,
+        at: SourceLocation,
+    },
+    #[error("Pattern at {at} would be larger than {limit} cells")]
+    TooManyCells { limit: usize, at: SourceLocation },
+    #[error("Life 1.05 block at {at} is not placed like #P -1 -1")]
+    InvalidLife105Block { at: SourceLocation },
+    #[error("Life 1.06 entry at {at} is not a pair of coordinates like -1 2")]
+    InvalidLife106Line { at: SourceLocation },
+}
+
+impl TextLoadError {
+    /// Place of the problem for errors caused by the content of the file.
+    pub fn location(&self) -> Option<&SourceLocation> {
+        match self {
+            Self::IoError(_)
+            | Self::NoFileFound(_)
+            | Self::EmptyFile
+            | Self::MissingRleHeader
+            | Self::EmptyMacrocell
+            | Self::NoCells(_) => None,
+            Self::NotValidCellChar { at, .. }
+            | Self::UnEqualWidth { at, .. }
+            | Self::InvalidRleHeader { at }
+            | Self::InvalidRleRule { at, .. }
+            | Self::InvalidRleToken { at, .. }
+            | Self::RleRowTooWide { at, .. }
+            | Self::RleTooManyRows { at, .. }
+            | Self::RleStateOutOfRange { at, .. }
+            | Self::MissingRleEnd { at }
+            | Self::MissingMacrocellHeader { at }
+            | Self::InvalidMacrocellRule { at, .. }
+            | Self::InvalidMacrocellNode { at }
+            | Self::InvalidLife105Rule { at, .. }
+            | Self::TooManyCells { at, .. }
+            | Self::InvalidLife105Block { at }
+            | Self::InvalidLife106Line { at } => Some(at),
+        }
+    }
+}
+
+/// Line and column of a problem in a loaded file together with the text of that line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    /// Number of the line, starting at 1 like in text editors.
+    pub line: usize,
+    /// Number of the char in the line, starting at 1. One past the end if something is missing.
+    pub column: usize,
+    pub text: String,
+}
+
+impl SourceLocation {
+    /// Location of the char with the given index in the line with the given index.
+    pub fn at_char(line_index: usize, line: &str, char_index: usize) -> Self {
+        Self {
+            line: line_index + 1,
+            column: char_index + 1,
+            text: line.to_owned(),
+        }
+    }
+
+    /// Location of the char which starts at the given byte of the line.
+    pub fn at_byte(line_index: usize, line: &str, byte_index: usize) -> Self {
+        let char_index = match line.get(..byte_index) {
+            Some(before) => before.chars().count(),
+            None => line.chars().count(),
+        };
+        Self::at_char(line_index, line, char_index)
+    }
+
+    /// Location just after the last char of the line.
+    pub fn after(line_index: usize, line: &str) -> Self {
+        Self::at_char(line_index, line, line.chars().count())
+    }
+}
+
+impl Display for SourceLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+/// Bytes of whitespace at the start of the text, to find where a trimmed part starts.
+pub fn indent_of(text: &str) -> usize {
+    text.len() - text.trim_start().len()
 }
@@ -0,0 +1,311 @@
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use eframe::egui::Ui;
+
+use crate::{
+    engine::{Engine, EngineKind, InvalidEngine},
+    grid::{Boundary, CellBounds, GridDrawSettings, GridTransform, LifeCell, Rule, TextData},
+};
+
+mod drawing;
+pub mod macrocell;
+mod node;
+
+use macrocell::Macrocell;
+use node::{Node, NodeCache};
+
+/// The smallest root still leaves room for the quarters of quarters a step needs.
+const MIN_LEVEL: u32 = 3;
+/// Keeps coordinates of the root inside of i64.
+const MAX_STEP_EXPONENT: u32 = 48;
+/// Nodes and results in the cache before it is cleared to free memory.
+const CACHE_LIMIT: usize = 4_000_000;
+
+/// Unbounded plane stored as a quadtree of shared squares.
+/// Calculated generations of every square are remembered,
+/// so repetitive patterns can jump by huge numbers of generations.
+pub struct HashLife {
+    root: Arc<Node>,
+    /// Row and column of the top left cell of the root.
+    origin: (i64, i64),
+    rule: Rule,
+    drawing: GridDrawSettings,
+    passed_ticks: u64,
+    edited_cells: usize,
+    /// Shared with all snapshots, so that restored keyframes still know the calculated steps.
+    cache: Arc<Mutex<NodeCache>>,
+}
+
+impl HashLife {
+    pub fn new(
+        text: &TextData,
+        rule: Rule,
+        drawing: GridDrawSettings,
+    ) -> Result<Self, InvalidEngine> {
+        validate_rule(rule)?;
+        let (height, width) = (text.height() as i64, text.width() as i64);
+        let level = level_for(height, width);
+
+        let mut cache = NodeCache::default();
+        let cell_at = |y: i64, x: i64| text.cell_at_y_x(y as usize, x as usize);
+        let root = cache.build(level, (0, 0), &cell_at, (height, width));
+
+        Ok(Self {
+            root,
+            origin: (0, 0),
+            rule,
+            drawing,
+            passed_ticks: 0,
+            edited_cells: 0,
+            cache: Arc::new(Mutex::new(cache)),
+        })
+    }
+
+    /// Takes over the quadtree of a Macrocell file as it is.
+    /// The middle of the root is put at row and column 0.
+    pub fn from_macrocell(
+        macrocell: Macrocell,
+        rule: Rule,
+        drawing: GridDrawSettings,
+    ) -> Result<Self, InvalidEngine> {
+        validate_rule(rule)?;
+        let (mut root, mut cache) = macrocell.into_parts();
+        while root.level() < MIN_LEVEL {
+            root = cache.expand(&root);
+        }
+        let half = root.size() / 2;
+
+        Ok(Self {
+            root,
+            origin: (-half, -half),
+            rule,
+            drawing,
+            passed_ticks: 0,
+            edited_cells: 0,
+            cache: Arc::new(Mutex::new(cache)),
+        })
+    }
+
+    /// Doubles the size of the root while keeping the cells in place.
+    fn expand(&mut self, cache: &mut NodeCache) {
+        let half = self.root.size() / 2;
+        self.root = cache.expand(&self.root);
+        self.origin = (self.origin.0 - half, self.origin.1 - half);
+    }
+
+    fn root_contains(&self, y: i64, x: i64) -> bool {
+        let size = self.root.size();
+        let (top, left) = self.origin;
+        (top..top + size).contains(&y) && (left..left + size).contains(&x)
+    }
+}
+
+impl Engine for HashLife {
+    fn kind(&self) -> EngineKind {
+        EngineKind::HashLife
+    }
+
+    fn snapshot(&self) -> Box<dyn Engine> {
+        Box::new(Self {
+            root: self.root.clone(),
+            origin: self.origin,
+            rule: self.rule,
+            drawing: self.drawing.clone(),
+            passed_ticks: self.passed_ticks,
+            edited_cells: self.edited_cells,
+            cache: self.cache.clone(),
+        })
+    }
+
+    /// Shared squares keep the tree far smaller than one node per alive cell,
+    /// which is taken as the rough upper bound.
+    fn keyframe_bytes(&self) -> usize {
+        let nodes = self.root.population() as usize;
+        nodes.saturating_mul(std::mem::size_of::<Node>())
+    }
+
+    fn tick(&mut self) {
+        self.step_pow2(0);
+    }
+
+    fn step_pow2(&mut self, exponent: u32) {
+        let shared = self.cache.clone();
+        let mut cache = lock(&shared);
+        if cache.len() > CACHE_LIMIT {
+            cache.clear();
+        }
+
+        // The result is the middle of the root. Alive cells start in the middle of the middle,
+        // so they can not grow out of the result within the generations of the step.
+        while self.root.level() < exponent + 2 || !cache.is_padded(&self.root) {
+            self.expand(&mut cache);
+        }
+        self.expand(&mut cache);
+
+        let quarter = self.root.size() / 4;
+        self.root = cache.step(&self.root, exponent, &self.rule);
+        self.origin = (self.origin.0 + quarter, self.origin.1 + quarter);
+        self.passed_ticks += 1 << exponent;
+    }
+
+    fn max_step_exponent(&self) -> u32 {
+        MAX_STEP_EXPONENT
+    }
+
+    fn passed_tick(&self) -> u64 {
+        self.passed_ticks
+    }
+
+    fn population(&self) -> u64 {
+        self.root.population()
+    }
+
+    fn cell_at(&self, y: i64, x: i64) -> LifeCell {
+        if self.root_contains(y, x) {
+            self.root.cell_at(y - self.origin.0, x - self.origin.1)
+        } else {
+            LifeCell::Dead
+        }
+    }
+
+    fn set_cell_at(&mut self, y: i64, x: i64, cell: LifeCell) {
+        if self.cell_at(y, x) == cell {
+            return;
+        }
+        let shared = self.cache.clone();
+        let mut cache = lock(&shared);
+        while !self.root_contains(y, x) {
+            self.expand(&mut cache);
+        }
+        let (top, left) = self.origin;
+        self.root = cache.set_cell(&self.root, y - top, x - left, cell);
+        self.edited_cells += 1;
+    }
+
+    fn edited_cells(&self) -> usize {
+        self.edited_cells
+    }
+
+    fn bounds(&self) -> CellBounds {
+        let (top, left) = self.origin;
+        match self.root.alive_bounds() {
+            Some((min_y, min_x, max_y, max_x)) => CellBounds {
+                top: top + min_y,
+                left: left + min_x,
+                height: (max_y - min_y + 1) as u64,
+                width: (max_x - min_x + 1) as u64,
+            },
+            None => CellBounds {
+                top,
+                left,
+                height: 0,
+                width: 0,
+            },
+        }
+    }
+
+    fn draw_at(&self, ui: &mut Ui, transform: GridTransform) {
+        HashLife::draw_at(self, ui, transform);
+    }
+
+    fn drawing(&self) -> &GridDrawSettings {
+        &self.drawing
+    }
+
+    fn set_drawing(&mut self, drawing: GridDrawSettings) {
+        self.drawing = drawing;
+    }
+
+    fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    fn boundary(&self) -> Option<Boundary> {
+        None
+    }
+
+    fn to_macrocell(&self) -> String {
+        macrocell::encode(&self.root, self.rule)
+    }
+}
+
+fn validate_rule(rule: Rule) -> Result<(), InvalidEngine> {
+    if rule.larger_than_life().is_some() {
+        return Err(InvalidEngine::LargerThanLife(rule));
+    }
+    if rule.is_hexagonal() {
+        return Err(InvalidEngine::HexagonalRule(rule));
+    }
+    if rule.is_birth(0) {
+        return Err(InvalidEngine::BirthWithoutNeighbours);
+    }
+    if rule.is_generations() {
+        return Err(InvalidEngine::GenerationsRule(rule));
+    }
+    Ok(())
+}
+
+/// A panic while the cache was locked leaves it consistent, it only misses some nodes.
+fn lock(cache: &Mutex<NodeCache>) -> MutexGuard<'_, NodeCache> {
+    cache.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Level of the smallest root which holds the given rows and columns.
+fn level_for(height: i64, width: i64) -> u32 {
+    (height.max(width).max(1) as u64)
+        .next_power_of_two()
+        .trailing_zeros()
+        .max(MIN_LEVEL)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::{rle, Grid};
+
+    /// R-pentomino in the middle of a grid large enough that it never reaches the edges.
+    fn r_pentomino() -> TextData {
+        rle::parse("x = 200, y = 200, rule = B3/S23\n100$100b2o$99b2o$100bo!\n").unwrap()
+    }
+
+    fn alive_cells(engine: &dyn Engine, size: i64) -> Vec<(i64, i64)> {
+        (0..size)
+            .flat_map(|y| (0..size).map(move |x| (y, x)))
+            .filter(|&(y, x)| engine.cell_at(y, x) == LifeCell::Alive)
+            .collect()
+    }
+
+    #[test]
+    fn steps_like_the_grid_for_every_exponent() {
+        let rule = Rule::conway();
+        let drawing = GridDrawSettings::default();
+        let mut hash_life = HashLife::new(&r_pentomino(), rule, drawing.clone()).unwrap();
+        let mut grid = Grid::new(r_pentomino(), rule, Boundary::Plane, drawing);
+
+        for exponent in [0, 1, 3, 0, 5, 2, 4] {
+            hash_life.step_pow2(exponent);
+            grid.step_pow2(exponent);
+            assert_eq!(hash_life.passed_tick(), grid.passed_tick());
+            assert_eq!(hash_life.population(), grid.population());
+            assert_eq!(alive_cells(&hash_life, 200), alive_cells(&grid, 200));
+        }
+    }
+
+    #[test]
+    fn snapshots_step_like_the_original() {
+        let rule = Rule::conway();
+        let mut hash_life =
+            HashLife::new(&r_pentomino(), rule, GridDrawSettings::default()).unwrap();
+        hash_life.step_pow2(4);
+        let mut copy = hash_life.snapshot();
+        hash_life.step_pow2(5);
+        // The copy shares the cache, so the same step is only looked up.
+        let cached = lock(&hash_life.cache).len();
+        copy.step_pow2(5);
+        assert_eq!(lock(&hash_life.cache).len(), cached);
+        assert_eq!(
+            alive_cells(&hash_life, 200),
+            alive_cells(copy.as_ref(), 200)
+        );
+    }
+}
@@ -0,0 +1,76 @@
+use eframe::{
+    egui::Ui,
+    epaint::{Color32, Mesh, Rect, Shape},
+};
+
+use super::{HashLife, Node};
+use crate::grid::{renderer, GridTransform, LifeCell};
+
+impl HashLife {
+    /// Draws the visible part of the plane as one mesh.
+    /// Squares without alive cells or outside of the clip rect are skipped as a whole,
+    /// squares smaller than a pixel are drawn as one pixel.
+    pub fn draw_at(&self, ui: &mut Ui, transform: GridTransform) {
+        let clip = ui.clip_rect();
+        let mut mesh = Mesh::default();
+        mesh.add_colored_rect(clip, self.drawing.color_for(LifeCell::Dead));
+
+        let alive_color = self.drawing.color_for(LifeCell::Alive);
+        draw_node(
+            &mut mesh,
+            transform,
+            clip,
+            &self.root,
+            self.origin,
+            alive_color,
+        );
+
+        let (min_y, min_x) = transform.cell_at_position(clip.min);
+        let (max_y, max_x) = transform.cell_at_position(clip.max);
+        let lines = (min_y..max_y + 1, min_x..max_x + 1);
+        renderer::add_grid_lines(&mut mesh, transform, lines, self.drawing.stroke_color);
+
+        ui.painter().add(Shape::mesh(mesh));
+    }
+}
+
+fn draw_node(
+    mesh: &mut Mesh,
+    transform: GridTransform,
+    clip: Rect,
+    node: &Node,
+    (top, left): (i64, i64),
+    color: Color32,
+) {
+    if node.population() == 0 {
+        return;
+    }
+    let size = node.size();
+    let rect = Rect::from_min_max(
+        transform.cell_min(top, left),
+        transform.cell_min(top + size, left + size),
+    );
+    if !clip.intersects(rect) {
+        return;
+    }
+
+    match node.children() {
+        Some(children) if rect.width() > 1. => {
+            let half = size / 2;
+            let corners = [
+                (top, left),
+                (top, left + half),
+                (top + half, left),
+                (top + half, left + half),
+            ];
+            for (child, corner) in children.iter().zip(corners) {
+                draw_node(mesh, transform, clip, child, corner, color);
+            }
+        }
+        _ => {
+            // Keeps squares smaller than a pixel visible.
+            let rect = Rect::from_min_size(rect.min, rect.size().max(eframe::epaint::vec2(1., 1.)));
+            mesh.add_colored_rect(rect, color);
+        }
+    }
+}
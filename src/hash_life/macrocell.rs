@@ -0,0 +1,303 @@
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use crate::grid::{
+    read_file,
+    text_load_error::{indent_of, SourceLocation, TextLoadError},
+    CellBounds, LifeCell, Rule,
+};
+
+use super::node::{Node, NodeCache};
+
+const MACROCELL_HEADER: &str = "[M2]";
+const MACROCELL_DEAD: char = '.';
+const MACROCELL_ALIVE: char = '*';
+const MACROCELL_END_OF_ROW: char = '$';
+const MACROCELL_COMMENT: char = '#';
+/// Squares of 8 by 8 cells are written as rows of cells, larger ones by their quarters.
+const LEAF_LEVEL: u32 = 3;
+/// Larger squares would leave no room for stepping with coordinates in i64.
+const MAX_LEVEL: u32 = 56;
+
+/// Quadtree read from a Macrocell file of Golly, kept as shared squares
+/// so that huge repetitive patterns are never expanded into single cells.
+pub struct Macrocell {
+    root: Arc<Node>,
+    rule: Option<Rule>,
+    cache: NodeCache,
+}
+
+impl Macrocell {
+    pub fn is_macrocell_file(path: &Path) -> bool {
+        path.extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("mc"))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, TextLoadError> {
+        let text = read_file(path)?;
+        Self::parse(&text)
+    }
+
+    /// Parses a pattern in the Macrocell format.
+    ///
+    /// ```text
+    /// [M2] (golly 4.2)
+    /// #R B3/S23
+    /// $$$$$.*$..*$***$
+    /// 4 0 0 0 1
+    /// ```
+    ///
+    /// Every line after the header is a node which is numbered from 1 on.
+    /// Squares of 8 by 8 cells are given by their rows, larger squares by their level
+    /// and the numbers of their quarters, 0 being an empty quarter. The last node is the root.
+    pub fn parse(text: &str) -> Result<Self, TextLoadError> {
+        let mut lines = text.lines().enumerate();
+        let first_line = lines.next().map_or("", |(_, line)| line);
+        if !first_line.trim_start().starts_with(MACROCELL_HEADER) {
+            let at = SourceLocation::at_char(0, first_line, 0);
+            return Err(TextLoadError::MissingMacrocellHeader { at });
+        }
+
+        let mut cache = NodeCache::default();
+        let mut nodes: Vec<Arc<Node>> = Vec::new();
+        let mut rule = None;
+        for (index, line) in lines {
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            let indent = indent_of(line);
+            if let Some(rule_string) = trimmed.strip_prefix("#R") {
+                let rule_start = indent + "#R".len() + indent_of(rule_string);
+                let rule_string = rule_string.trim();
+                let parsed = rule_string.parse::<Rule>().map_err(|error| {
+                    let at = SourceLocation::at_byte(index, line, rule_start);
+                    TextLoadError::InvalidMacrocellRule { error, at }
+                })?;
+                rule = Some(parsed);
+            } else if !trimmed.starts_with(MACROCELL_COMMENT) {
+                let node = parse_node(trimmed, &nodes, &mut cache).ok_or_else(|| {
+                    TextLoadError::InvalidMacrocellNode {
+                        at: SourceLocation::at_byte(index, line, indent),
+                    }
+                })?;
+                nodes.push(node);
+            }
+        }
+
+        let root = nodes.pop().ok_or(TextLoadError::EmptyMacrocell)?;
+        Ok(Self { root, rule, cache })
+    }
+
+    /// Rule given by the `#R` line.
+    pub fn rule(&self) -> Option<Rule> {
+        self.rule
+    }
+
+    /// Root of the quadtree together with the cache which knows all of its nodes.
+    pub fn into_parts(self) -> (Arc<Node>, NodeCache) {
+        (self.root, self.cache)
+    }
+}
+
+/// Returns none if the line is neither a square of cells nor a node of earlier nodes.
+fn parse_node(line: &str, nodes: &[Arc<Node>], cache: &mut NodeCache) -> Option<Arc<Node>> {
+    if line.starts_with([MACROCELL_DEAD, MACROCELL_ALIVE, MACROCELL_END_OF_ROW]) {
+        return parse_leaf(line, cache);
+    }
+
+    let numbers: Vec<usize> = line
+        .split_whitespace()
+        .map(str::parse)
+        .collect::<Result<_, _>>()
+        .ok()?;
+    let &[level, nw, ne, sw, se] = numbers.as_slice() else {
+        return None;
+    };
+    let level = u32::try_from(level).ok()?;
+    if !(LEAF_LEVEL + 1..=MAX_LEVEL).contains(&level) {
+        return None;
+    }
+
+    let mut quarter = |number: usize| match number {
+        0 => Some(cache.empty(level - 1)),
+        number => nodes
+            .get(number - 1)
+            .filter(|node| node.level() == level - 1)
+            .cloned(),
+    };
+    let (nw, ne, sw, se) = (quarter(nw)?, quarter(ne)?, quarter(sw)?, quarter(se)?);
+    Some(cache.join(nw, ne, sw, se))
+}
+
+/// Square of 8 by 8 cells whose rows end with `$`, leaving out dead cells at their end.
+fn parse_leaf(line: &str, cache: &mut NodeCache) -> Option<Arc<Node>> {
+    let size = 1 << LEAF_LEVEL;
+    let mut alive = [[false; 1 << LEAF_LEVEL]; 1 << LEAF_LEVEL];
+    let (mut y, mut x) = (0, 0);
+    for token in line.chars() {
+        match token {
+            MACROCELL_END_OF_ROW => (y, x) = (y + 1, 0),
+            MACROCELL_DEAD | MACROCELL_ALIVE if y < size && x < size => {
+                alive[y][x] = token == MACROCELL_ALIVE;
+                x += 1;
+            }
+            _ => return None,
+        }
+    }
+
+    let cell_at = |y: i64, x: i64| {
+        if alive[y as usize][x as usize] {
+            LifeCell::Alive
+        } else {
+            LifeCell::Dead
+        }
+    };
+    let size = size as i64;
+    Some(cache.build(LEAF_LEVEL, (0, 0), &cell_at, (size, size)))
+}
+
+/// Writes the quadtree in the Macrocell format, every shared square only once.
+pub fn encode(root: &Arc<Node>, rule: Rule) -> String {
+    let mut output = format!("{} (game_of_life)\n#R {}\n", MACROCELL_HEADER, rule);
+    if root.population() == 0 {
+        // An empty square still needs a node to be a valid file.
+        output.push(MACROCELL_END_OF_ROW);
+        output.push('\n');
+        return output;
+    }
+    let mut numbers = HashMap::new();
+    write_node(root, &mut numbers, &mut output);
+    output
+}
+
+/// Writes the cells in the bounds of any engine by building a quadtree of them first.
+pub fn encode_cells(
+    bounds: CellBounds,
+    cell_at: impl Fn(i64, i64) -> LifeCell,
+    rule: Rule,
+) -> String {
+    let (height, width) = (bounds.height as i64, bounds.width as i64);
+    let level = super::level_for(height, width);
+    let mut cache = NodeCache::default();
+    let cell_at = |y: i64, x: i64| cell_at(bounds.top + y, bounds.left + x);
+    let root = cache.build(level, (0, 0), &cell_at, (height, width));
+    encode(&root, rule)
+}
+
+/// Writes the quarters of a node before the node and returns its number, 0 for empty nodes.
+fn write_node(node: &Arc<Node>, numbers: &mut HashMap<usize, usize>, output: &mut String) -> usize {
+    if node.population() == 0 {
+        return 0;
+    }
+    let key = Arc::as_ptr(node) as usize;
+    if let Some(&number) = numbers.get(&key) {
+        return number;
+    }
+
+    if node.level() == LEAF_LEVEL {
+        let size = node.size();
+        for y in 0..size {
+            let row: String = (0..size)
+                .map(|x| match node.cell_at(y, x) {
+                    LifeCell::Alive => MACROCELL_ALIVE,
+                    _ => MACROCELL_DEAD,
+                })
+                .collect();
+            output.push_str(row.trim_end_matches(MACROCELL_DEAD));
+            output.push(MACROCELL_END_OF_ROW);
+        }
+        // Empty rows at the end are left out.
+        while output.ends_with(&format!("{0}{0}", MACROCELL_END_OF_ROW)) {
+            output.pop();
+        }
+    } else {
+        let children = node.children().expect("Node above the leaves has quarters");
+        let [nw, ne, sw, se] = children
+            .each_ref()
+            .map(|child| write_node(child, numbers, output));
+        output.push_str(&format!("{} {} {} {} {}", node.level(), nw, ne, sw, se));
+    }
+    output.push('\n');
+
+    let number = numbers.len() + 1;
+    numbers.insert(key, number);
+    number
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(text: &str) -> (usize, usize) {
+        let error = Macrocell::parse(text).err().expect("Pattern is invalid");
+        let at = error.location().expect("Error has a location");
+        (at.line, at.column)
+    }
+
+    #[test]
+    fn encoded_quadtrees_parse_to_the_same_cells() {
+        let mut state = 0x6a09_e667_f3bc_c908_u64;
+        let alive: Vec<Vec<bool>> = (0..40)
+            .map(|_| {
+                (0..70)
+                    .map(|_| {
+                        state ^= state << 13;
+                        state ^= state >> 7;
+                        state ^= state << 17;
+                        state.is_multiple_of(5)
+                    })
+                    .collect()
+            })
+            .collect();
+        let cell_at = |y: i64, x: i64| {
+            if alive[y as usize][x as usize] {
+                LifeCell::Alive
+            } else {
+                LifeCell::Dead
+            }
+        };
+        let mut cache = NodeCache::default();
+        let root = cache.build(7, (0, 0), &cell_at, (40, 70));
+        let rule: Rule = "B36/S23".parse().unwrap();
+
+        let parsed = Macrocell::parse(&encode(&root, rule)).expect("Encoded pattern is valid");
+        assert_eq!(parsed.rule(), Some(rule));
+        let (parsed, _) = parsed.into_parts();
+        assert_eq!(parsed.level(), root.level());
+        assert_eq!(parsed.population(), root.population());
+        for y in 0..root.size() {
+            for x in 0..root.size() {
+                assert_eq!(parsed.cell_at(y, x), root.cell_at(y, x), "{} {}", y, x);
+            }
+        }
+    }
+
+    #[test]
+    fn writes_shared_squares_once() {
+        let cell_at = |y: i64, x: i64| {
+            if y % 64 == 1 && x % 64 == 1 {
+                LifeCell::Alive
+            } else {
+                LifeCell::Dead
+            }
+        };
+        let mut cache = NodeCache::default();
+        let root = cache.build(10, (0, 0), &cell_at, (1024, 1024));
+        let encoded = encode(&root, Rule::conway());
+        // Header, rule, the one leaf and one node for each of the levels 4 to 10.
+        assert_eq!(encoded.lines().count(), 10);
+
+        let (parsed, _) = Macrocell::parse(&encoded).unwrap().into_parts();
+        assert_eq!(parsed.population(), 16 * 16);
+        assert_eq!(parsed.cell_at(65, 129), LifeCell::Alive);
+    }
+
+    #[test]
+    fn reports_the_line_and_column() {
+        assert_eq!(location("#R B3/S23\n$.*$\n"), (1, 1));
+        assert_eq!(location("[M2]\n#R  B3/S2x\n$.*$\n"), (2, 5));
+        assert_eq!(location("[M2]\n$.*$\n  4 1 0 0 9\n"), (3, 3));
+        assert_eq!(location("[M2]\n#C comment\n$.*x$\n"), (3, 1));
+        assert_eq!(location("[M2]\n$.*$\n3 1 1 1 1\n"), (3, 1));
+    }
+}
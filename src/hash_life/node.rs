@@ -0,0 +1,396 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::grid::{LifeCell, Rule, NEIGHBOUR_OFFSETS};
+
+const NW: usize = 0;
+const NE: usize = 1;
+const SW: usize = 2;
+const SE: usize = 3;
+
+/// Square of 2 to the power of level cells. Level 0 is a single cell.
+/// Equal squares are shared, so a node is never changed after it was created.
+pub struct Node {
+    level: u32,
+    population: u64,
+    /// North west, north east, south west and south east quarter. None for single cells.
+    children: Option<[Arc<Node>; 4]>,
+}
+
+impl Node {
+    pub fn level(&self) -> u32 {
+        self.level
+    }
+
+    pub fn population(&self) -> u64 {
+        self.population
+    }
+
+    pub fn children(&self) -> Option<&[Arc<Node>; 4]> {
+        self.children.as_ref()
+    }
+
+    pub fn size(&self) -> i64 {
+        1 << self.level
+    }
+
+    /// Returns the cell at the row and column relative to the top left corner of this node.
+    pub fn cell_at(&self, y: i64, x: i64) -> LifeCell {
+        let mut node = self;
+        let (mut y, mut x) = (y, x);
+        while let Some(children) = &node.children {
+            if node.population == 0 {
+                break;
+            }
+            let half = node.size() / 2;
+            let (south, east) = (y >= half, x >= half);
+            node = &children[quarter(south, east)];
+            if south {
+                y -= half;
+            }
+            if east {
+                x -= half;
+            }
+        }
+
+        if node.population > 0 {
+            LifeCell::Alive
+        } else {
+            LifeCell::Dead
+        }
+    }
+
+    /// Returns the smallest and largest row and column with an alive cell
+    /// relative to the top left corner of this node.
+    pub fn alive_bounds(&self) -> Option<(i64, i64, i64, i64)> {
+        if self.population == 0 {
+            return None;
+        }
+        Some((
+            self.first_alive_line(true, false),
+            self.first_alive_line(false, false),
+            self.first_alive_line(true, true),
+            self.first_alive_line(false, true),
+        ))
+    }
+
+    /// Searches the first row or column with an alive cell from the top, left, bottom or right.
+    /// Only call it on nodes with alive cells.
+    fn first_alive_line(&self, rows: bool, from_end: bool) -> i64 {
+        let Some(children) = &self.children else {
+            return 0;
+        };
+        let half = self.size() / 2;
+        // Quarters of the half which is searched first and of the other half.
+        let (near, far) = match (rows, from_end) {
+            (true, false) => ([NW, NE], [SW, SE]),
+            (true, true) => ([SW, SE], [NW, NE]),
+            (false, false) => ([NW, SW], [NE, SE]),
+            (false, true) => ([NE, SE], [NW, SW]),
+        };
+        let (near_offset, far_offset) = if from_end { (half, 0) } else { (0, half) };
+
+        let search = |quarters: [usize; 2], offset: i64| {
+            let found = quarters
+                .iter()
+                .map(|&quarter| &children[quarter])
+                .filter(|child| child.population > 0)
+                .map(|child| child.first_alive_line(rows, from_end));
+            let found = if from_end { found.max() } else { found.min() };
+            found.map(|line| line + offset)
+        };
+
+        search(near, near_offset)
+            .or_else(|| search(far, far_offset))
+            .expect("Node with alive cells has no alive quarter")
+    }
+}
+
+fn quarter(south: bool, east: bool) -> usize {
+    match (south, east) {
+        (false, false) => NW,
+        (false, true) => NE,
+        (true, false) => SW,
+        (true, true) => SE,
+    }
+}
+
+fn key_of(node: &Arc<Node>) -> usize {
+    Arc::as_ptr(node) as usize
+}
+
+/// Makes sure equal nodes are only created once and remembers calculated generations.
+/// Nodes are identified by their address, so every stored node is kept alive by the cache.
+pub struct NodeCache {
+    nodes: HashMap<[usize; 4], Arc<Node>>,
+    /// Node and exponent of the step to the node and its result.
+    results: HashMap<(usize, u32), (Arc<Node>, Arc<Node>)>,
+    empty: Vec<Arc<Node>>,
+    cells: [Arc<Node>; 2],
+}
+
+impl Default for NodeCache {
+    fn default() -> Self {
+        let cell = |population| {
+            Arc::new(Node {
+                level: 0,
+                population,
+                children: None,
+            })
+        };
+        let dead = cell(0);
+        Self {
+            nodes: HashMap::new(),
+            results: HashMap::new(),
+            empty: vec![dead.clone()],
+            cells: [dead, cell(1)],
+        }
+    }
+}
+
+impl NodeCache {
+    pub fn len(&self) -> usize {
+        self.nodes.len() + self.results.len()
+    }
+
+    /// Forgets all nodes and results except the empty ones to free memory.
+    /// Nodes which are still in use stay valid.
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.results.clear();
+    }
+
+    pub fn cell(&self, cell: LifeCell) -> Arc<Node> {
+        match cell {
+            LifeCell::Dead | LifeCell::Dying(_) | LifeCell::State(_) => self.cells[0].clone(),
+            LifeCell::Alive => self.cells[1].clone(),
+        }
+    }
+
+    pub fn join(
+        &mut self,
+        nw: Arc<Node>,
+        ne: Arc<Node>,
+        sw: Arc<Node>,
+        se: Arc<Node>,
+    ) -> Arc<Node> {
+        let key = [key_of(&nw), key_of(&ne), key_of(&sw), key_of(&se)];
+        if let Some(node) = self.nodes.get(&key) {
+            return node.clone();
+        }
+
+        let node = Arc::new(Node {
+            level: nw.level + 1,
+            population: nw.population + ne.population + sw.population + se.population,
+            children: Some([nw, ne, sw, se]),
+        });
+        self.nodes.insert(key, node.clone());
+        node
+    }
+
+    pub fn empty(&mut self, level: u32) -> Arc<Node> {
+        while self.empty.len() <= level as usize {
+            let smaller = self.empty.last().unwrap().clone();
+            let bigger = self.join(smaller.clone(), smaller.clone(), smaller.clone(), smaller);
+            self.empty.push(bigger);
+        }
+        self.empty[level as usize].clone()
+    }
+
+    /// Builds a node of the given level whose top left corner is at the row and column.
+    pub fn build(
+        &mut self,
+        level: u32,
+        (top, left): (i64, i64),
+        cell_at: &impl Fn(i64, i64) -> LifeCell,
+        (height, width): (i64, i64),
+    ) -> Arc<Node> {
+        let size = 1i64 << level;
+        if top >= height || left >= width || top + size <= 0 || left + size <= 0 {
+            return self.empty(level);
+        }
+        if level == 0 {
+            return self.cell(cell_at(top, left));
+        }
+
+        let half = size / 2;
+        let bounds = (height, width);
+        let nw = self.build(level - 1, (top, left), cell_at, bounds);
+        let ne = self.build(level - 1, (top, left + half), cell_at, bounds);
+        let sw = self.build(level - 1, (top + half, left), cell_at, bounds);
+        let se = self.build(level - 1, (top + half, left + half), cell_at, bounds);
+        self.join(nw, ne, sw, se)
+    }
+
+    /// Returns a copy of the node with one cell changed.
+    pub fn set_cell(&mut self, node: &Arc<Node>, y: i64, x: i64, cell: LifeCell) -> Arc<Node> {
+        let Some(children) = &node.children else {
+            return self.cell(cell);
+        };
+        let half = node.size() / 2;
+        let (south, east) = (y >= half, x >= half);
+        let index = quarter(south, east);
+        let (inner_y, inner_x) = (
+            y - if south { half } else { 0 },
+            x - if east { half } else { 0 },
+        );
+
+        let mut children = children.clone();
+        children[index] = self.set_cell(&children[index], inner_y, inner_x, cell);
+        let [nw, ne, sw, se] = children;
+        self.join(nw, ne, sw, se)
+    }
+
+    /// Returns the middle of the node with half of its size.
+    pub fn centre(&mut self, node: &Arc<Node>) -> Arc<Node> {
+        let [nw, ne, sw, se] = grand_children(node);
+        self.join(
+            nw[SE].clone(),
+            ne[SW].clone(),
+            sw[NE].clone(),
+            se[NW].clone(),
+        )
+    }
+
+    /// Returns a node with twice the size which has the given node in its middle.
+    pub fn expand(&mut self, node: &Arc<Node>) -> Arc<Node> {
+        let [nw, ne, sw, se] = node.children.clone().expect("Can not expand a single cell");
+        let empty = self.empty(node.level - 1);
+        let nw = self.join(empty.clone(), empty.clone(), empty.clone(), nw);
+        let ne = self.join(empty.clone(), empty.clone(), ne, empty.clone());
+        let sw = self.join(empty.clone(), sw, empty.clone(), empty.clone());
+        let se = self.join(se, empty.clone(), empty.clone(), empty);
+        self.join(nw, ne, sw, se)
+    }
+
+    /// True if all alive cells are inside the middle quarter of the node.
+    pub fn is_padded(&self, node: &Node) -> bool {
+        if node.level < 2 {
+            return node.population == 0;
+        }
+        let [nw, ne, sw, se] = grand_children(node);
+        let inner = nw[SE].population + ne[SW].population + sw[NE].population + se[NW].population;
+        inner == node.population
+    }
+
+    /// Returns the middle of the node with half of its size
+    /// after 2 to the power of the exponent generations.
+    /// The exponent can be at most the level of the node minus 2.
+    pub fn step(&mut self, node: &Arc<Node>, exponent: u32, rule: &Rule) -> Arc<Node> {
+        let level = node.level;
+        debug_assert!(level >= 2 && exponent <= level - 2);
+        if node.population == 0 {
+            return self.empty(level - 1);
+        }
+        let key = (key_of(node), exponent);
+        if let Some((_, result)) = self.results.get(&key) {
+            return result.clone();
+        }
+
+        let result = if level == 2 {
+            self.step_smallest(node, rule)
+        } else {
+            let [nw, ne, sw, se] = grand_children(node);
+            let [n00, n02, n20, n22] = node.children.clone().unwrap();
+            let n01 = self.join(
+                nw[NE].clone(),
+                ne[NW].clone(),
+                nw[SE].clone(),
+                ne[SW].clone(),
+            );
+            let n10 = self.join(
+                nw[SW].clone(),
+                nw[SE].clone(),
+                sw[NW].clone(),
+                sw[NE].clone(),
+            );
+            let n11 = self.join(
+                nw[SE].clone(),
+                ne[SW].clone(),
+                sw[NE].clone(),
+                se[NW].clone(),
+            );
+            let n12 = self.join(
+                ne[SW].clone(),
+                ne[SE].clone(),
+                se[NW].clone(),
+                se[NE].clone(),
+            );
+            let n21 = self.join(
+                sw[NE].clone(),
+                se[NW].clone(),
+                sw[SE].clone(),
+                se[SW].clone(),
+            );
+            let nine = [n00, n01, n02, n10, n11, n12, n20, n21, n22];
+
+            // At full speed both halves of the step advance by a quarter of the node size.
+            // Slower steps only advance in the second half.
+            let full_speed = exponent == level - 2;
+            let nine: Vec<Arc<Node>> = nine
+                .iter()
+                .map(|sub_node| {
+                    if full_speed {
+                        self.step(sub_node, level - 3, rule)
+                    } else {
+                        self.centre(sub_node)
+                    }
+                })
+                .collect();
+            let second_exponent = if full_speed { level - 3 } else { exponent };
+
+            let mut quarter_of = |a: usize, b: usize, c: usize, d: usize| {
+                let joined = self.join(
+                    nine[a].clone(),
+                    nine[b].clone(),
+                    nine[c].clone(),
+                    nine[d].clone(),
+                );
+                self.step(&joined, second_exponent, rule)
+            };
+            let nw = quarter_of(0, 1, 3, 4);
+            let ne = quarter_of(1, 2, 4, 5);
+            let sw = quarter_of(3, 4, 6, 7);
+            let se = quarter_of(4, 5, 7, 8);
+            self.join(nw, ne, sw, se)
+        };
+
+        self.results.insert(key, (node.clone(), result.clone()));
+        result
+    }
+
+    /// Calculates the middle 2 x 2 cells of a 4 x 4 node after one generation.
+    fn step_smallest(&mut self, node: &Node, rule: &Rule) -> Arc<Node> {
+        let mut alive = [[false; 4]; 4];
+        for (y, row) in alive.iter_mut().enumerate() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                *cell = node.cell_at(y as i64, x as i64) == LifeCell::Alive;
+            }
+        }
+
+        let next = |y: usize, x: usize| {
+            let neighbourhood = NEIGHBOUR_OFFSETS
+                .iter()
+                .enumerate()
+                .filter(|(_, (offset_y, offset_x))| {
+                    alive[(y as isize + offset_y) as usize][(x as isize + offset_x) as usize]
+                })
+                .fold(0, |neighbourhood, (bit, _)| neighbourhood | 1 << bit);
+            let cell = if alive[y][x] {
+                LifeCell::Alive
+            } else {
+                LifeCell::Dead
+            };
+            self.cell(rule.next_cell(cell, neighbourhood))
+        };
+        let (nw, ne, sw, se) = (next(1, 1), next(1, 2), next(2, 1), next(2, 2));
+        self.join(nw, ne, sw, se)
+    }
+}
+
+/// Returns the quarters of every quarter of the node.
+fn grand_children(node: &Node) -> [[Arc<Node>; 4]; 4] {
+    let children = node.children.as_ref().expect("Node has no quarters");
+    children
+        .clone()
+        .map(|child| child.children.clone().expect("Quarter has no quarters"))
+}
@@ -1,8 +1,11 @@
 mod draw_utils;
+mod engine;
 mod game_of_life_window;
 mod game_view;
 mod grid;
+mod hash_life;
 mod open_view;
+mod sparse;
 mod timer;
 
 use crate::open_view::OpenView;
@@ -13,6 +16,7 @@ use std::path::{Path, PathBuf};
 
 use eframe::egui;
 
+#[allow(clippy::large_enum_variant)]
 pub enum CurrentView {
     Game(GameView),
     Open(OpenView),
@@ -26,20 +30,37 @@ mod constans {
     pub const _INITIAL_NAME: &str = "initial.txt";
     pub const ALIVE_CHAR: char = 'x';
     pub const DEAD_CHAR: char = '*';
+    pub const DEFAULT_RULE: &str = "B3/S23";
     pub const MARGIN: f32 = 20.;
     pub const TICK_DURATION: Duration = Duration::from_secs(1);
 
     pub const BTN_TEXT_PLAY: &str = "Play";
     pub const BTN_RESUME_TXT: &str = "Resume";
     pub const BTN_PAUSE_TXT: &str = "Pause";
+    pub const BTN_PREVIOUS_TXT: &str = "Previous";
     pub const BTN_NEXT_TXT: &str = "Next";
+    pub const STEP_EXPONENT_TXT: &str = "Step 2^";
     pub const BTN_RESET_TXT: &str = "Reset";
     pub const BTN_BACK_TXT: &str = "Back";
     pub const BTN_CHOOSE_TXT: &str = "Choose";
+    pub const BTN_SAVE_TXT: &str = "Save";
+    pub const BTN_EXPORT_IMAGE_TXT: &str = "Export image";
+    pub const IMAGE_CELL_SIZE_TXT: &str = "Image cell size";
+    pub const IMAGE_GRID_LINES_TXT: &str = "Grid lines";
+    pub const MAX_IMAGE_CELL_SIZE: u32 = 64;
+    pub const BTN_FIT_TXT: &str = "Fit to window";
+    pub const PARALLEL_TXT: &str = "Parallel";
+    pub const RESET_TO_EDITED_TXT: &str = "Reset to edited";
+    pub const FOLLOW_TXT: &str = "Follow pattern";
+    pub const DETECT_PERIODS_TXT: &str = "Detect repetitions";
+    pub const PAUSE_ON_REPETITION_TXT: &str = "Pause when repeating";
+    pub const COLOURING_TXT: &str = "Colouring";
+    pub const HISTORY_TXT: &str = "History";
     pub const MISSING_PATH_TXT: &str = "<Missing path>";
 
     pub const GRID_SPACEING: &[f32; 2] = &[40.0, 4.0];
     pub const FONT_SIZE: f32 = 25.;
+    pub const SNIPPET_FONT_SIZE: f32 = 18.;
 
     pub const ERR_COLOR: Color32 = Color32::RED;
     pub const WARN_COLOR: Color32 = Color32::YELLOW;
@@ -1,7 +1,12 @@
 use eframe::egui::{self, Ui};
 use egui_file::FileDialog;
 
-use crate::{constans::TICK_DURATION, CurrentView};
+use crate::{
+    constans::{DEFAULT_RULE, TICK_DURATION},
+    engine::EngineKind,
+    grid::Boundary,
+    CurrentView,
+};
 
 mod data_file_state;
 mod drawing;
@@ -22,6 +27,13 @@ pub struct OpenView {
     alive_char_input: String,
     selected_time: SelectedTime,
     time_interval: String,
+    rule_input: String,
+    /// Char inputs for every state if the rule names a hand written automaton.
+    automaton_char_inputs: Vec<String>,
+    boundary: Boundary,
+    engine: EngineKind,
+    /// Reads plain text files with ragged rows, comments and trailing whitespace.
+    lenient: bool,
     game_file_state: DataFileState,
 }
 
@@ -39,6 +51,11 @@ impl Default for OpenView {
             dead_char_input: String::from(dead_char_code),
             alive_char_input: String::from(alive_char_code),
             time_interval: TICK_DURATION.as_secs().to_string(),
+            rule_input: DEFAULT_RULE.to_string(),
+            automaton_char_inputs: Vec::new(),
+            boundary: Default::default(),
+            engine: Default::default(),
+            lenient: false,
         }
     }
 }
@@ -60,6 +77,18 @@ impl OpenView {
             dead_char_input: dead_char_code.into(),
             alive_char_input: alive_char_code.into(),
             time_interval: gathered.time_interval.to_string(),
+            rule_input: match &gathered.automaton {
+                Some(automaton) => automaton.name().to_string(),
+                None => gathered.rule.to_string(),
+            },
+            automaton_char_inputs: gathered
+                .automaton_chars
+                .iter()
+                .map(|&char| char.into())
+                .collect(),
+            boundary: gathered.boundary,
+            engine: gathered.engine,
+            lenient: gathered.lenient,
         }
     }
 }
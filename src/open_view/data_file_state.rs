@@ -2,7 +2,9 @@ use std::path::PathBuf;
 
 use super::ValidationError;
 
+#[derive(Default)]
 pub enum DataFileState {
+    #[default]
     NotChoosen,
     Choosen {
         path: PathBuf,
@@ -13,11 +15,6 @@ pub enum DataFileState {
     },
 }
 
-impl Default for DataFileState {
-    fn default() -> Self {
-        Self::NotChoosen
-    }
-}
 impl From<(PathBuf, ValidationError)> for DataFileState {
     fn from(value: (PathBuf, ValidationError)) -> Self {
         DataFileState::Invalid {
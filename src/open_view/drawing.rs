@@ -1,12 +1,18 @@
-use std::error::Error;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use super::{DataFileState, GatheredOpenViewData, OpenView, TimeUnit};
-use crate::grid::{DeadAliveCharCell, Grid, GridDrawSettings, TextData};
+use crate::engine::{Engine, EngineKind, InvalidEngine};
+use crate::grid::{
+    self, text_load_error::SourceLocation, Automaton, Boundary, DeadAliveCharCell, Grid,
+    GridDrawSettings, Rule, StateCharCells, TextData,
+};
+use crate::hash_life::{macrocell::Macrocell, HashLife};
 use crate::open_view::{SelectedTime, ValidationError};
+use crate::sparse::SparseLife;
 use crate::{constans::*, draw_utils};
 use crate::{game_view::GameView, CurrentView};
-use eframe::egui::{self, Button, Ui};
+use eframe::egui::{self, text::LayoutJob, Button, FontId, RichText, TextFormat, Ui};
 use eframe::epaint::Color32;
 use egui_file::FileDialog;
 
@@ -79,10 +85,33 @@ fn draw_buttons(state: &mut OpenView, ui: &mut Ui, ctx: &egui::Context) -> Optio
         };
 
         let valid_number = try_parse_interval_time(state, &path)?;
-        let dead_alive_chars = validate_given_chars_for_game(state, &path)?;
-        let text_data = validate_file_content(dead_alive_chars, &path)?;
+        let automaton = grid::built_in(&state.rule_input);
+        let state_chars = validate_given_chars_for_game(state, automaton.as_deref(), &path)?;
+        let automaton_chars = match automaton {
+            Some(_) => state_chars.chars().to_vec(),
+            None => Vec::new(),
+        };
+        let rule = match automaton {
+            Some(_) => Rule::default(),
+            None => validate_rule(state, &path)?,
+        };
+        let (game, rule) = if Macrocell::is_macrocell_file(&path) {
+            // Macrocell files are kept as a quadtree instead of being expanded into cells.
+            let macrocell = Macrocell::load(&path).map_err(|error| (path.clone(), error.into()))?;
+            let rule = macrocell.rule().unwrap_or(rule);
+            let game = create_macrocell_engine(state, macrocell, rule, automaton.is_some())
+                .map_err(|error| (path.clone(), error.into()))?;
+            (game, rule)
+        } else {
+            let states = automaton
+                .as_ref()
+                .map_or(rule.states(), |automaton| automaton.states());
+            let text_data = validate_file_content(state_chars, state.lenient, states, &path)?;
+            let rule = text_data.rule().unwrap_or(rule);
+            let game = create_engine(state, text_data, rule, automaton.clone(), &path)?;
+            (game, rule)
+        };
         let time_interval = time_unit_from_selection(state.selected_time, valid_number);
-        let game = Grid::new(text_data, GridDrawSettings::default());
 
         let gathered = GatheredOpenViewData {
             alive_char_code: state.alive_char_code,
@@ -91,6 +120,12 @@ fn draw_buttons(state: &mut OpenView, ui: &mut Ui, ctx: &egui::Context) -> Optio
             path,
             selected_time: state.selected_time,
             time_interval,
+            rule,
+            automaton,
+            automaton_chars,
+            boundary: state.boundary,
+            engine: state.engine,
+            lenient: state.lenient,
         };
         return Ok(Some(CurrentView::Game(GameView::new(gathered))));
 
@@ -121,47 +156,145 @@ fn draw_buttons(state: &mut OpenView, ui: &mut Ui, ctx: &egui::Context) -> Optio
             }
         }
         fn validate_file_content(
-            dead_alive_cell_chars: DeadAliveCharCell,
+            state_chars: StateCharCells,
+            lenient: bool,
+            states: u8,
             path: &Path,
         ) -> Result<TextData, (PathBuf, ValidationError)> {
-            match TextData::new(path, dead_alive_cell_chars) {
+            match TextData::load(path, state_chars, lenient, states) {
                 Err(error) => Err((path.to_owned(), error.into())),
                 Ok(data) => Ok(data),
             }
         }
+        fn create_macrocell_engine(
+            state: &OpenView,
+            macrocell: Macrocell,
+            rule: Rule,
+            has_automaton: bool,
+        ) -> Result<Box<dyn Engine>, InvalidEngine> {
+            if state.engine != EngineKind::HashLife || has_automaton {
+                return Err(InvalidEngine::MacrocellFile);
+            }
+            let drawing = GridDrawSettings::default().with_states(rule.states());
+            HashLife::from_macrocell(macrocell, rule, drawing).map(|engine| Box::new(engine) as _)
+        }
+        fn create_engine(
+            state: &OpenView,
+            text_data: TextData,
+            rule: Rule,
+            automaton: Option<Arc<dyn Automaton>>,
+            path: &Path,
+        ) -> Result<Box<dyn Engine>, (PathBuf, ValidationError)> {
+            let created: Result<Box<dyn Engine>, ValidationError> = match (state.engine, automaton)
+            {
+                (EngineKind::Grid, Some(automaton)) => state
+                    .boundary
+                    .validate(text_data.height(), text_data.width(), None)
+                    .map(|_| {
+                        let drawing =
+                            GridDrawSettings::default().with_automaton(automaton.as_ref());
+                        let grid =
+                            Grid::with_automaton(text_data, automaton, state.boundary, drawing);
+                        Box::new(grid) as _
+                    })
+                    .map_err(ValidationError::from),
+                (_, Some(automaton)) => {
+                    Err(InvalidEngine::HandWrittenAutomaton(automaton.name()).into())
+                }
+                (engine, None) => {
+                    let drawing = GridDrawSettings::default().with_states(rule.states());
+                    match engine {
+                        EngineKind::Grid => state
+                            .boundary
+                            .validate(text_data.height(), text_data.width(), Some(rule))
+                            .map(|_| {
+                                Box::new(Grid::new(text_data, rule, state.boundary, drawing)) as _
+                            })
+                            .map_err(ValidationError::from),
+                        EngineKind::HashLife => HashLife::new(&text_data, rule, drawing)
+                            .map(|engine| Box::new(engine) as _)
+                            .map_err(ValidationError::from),
+                        EngineKind::Sparse => SparseLife::new(&text_data, rule, drawing)
+                            .map(|engine| Box::new(engine) as _)
+                            .map_err(ValidationError::from),
+                    }
+                }
+            };
+            created.map_err(|error| (path.to_owned(), error))
+        }
     }
 
     fn validate_given_chars_for_game(
         state: &mut OpenView,
+        automaton: Option<&dyn Automaton>,
         path: &Path,
-    ) -> Result<DeadAliveCharCell, (PathBuf, ValidationError)> {
-        match DeadAliveCharCell::new(&state.dead_char_input, &state.alive_char_input) {
+    ) -> Result<StateCharCells, (PathBuf, ValidationError)> {
+        let state_chars = match automaton {
+            Some(automaton) => StateCharCells::new(automaton, &state.automaton_char_inputs),
+            None => DeadAliveCharCell::new(&state.dead_char_input, &state.alive_char_input)
+                .map(StateCharCells::from),
+        };
+        match state_chars {
+            Err(error) => Err((path.to_owned(), error.into())),
+            Ok(valid_state_chars) => Ok(valid_state_chars),
+        }
+    }
+
+    fn validate_rule(state: &OpenView, path: &Path) -> Result<Rule, (PathBuf, ValidationError)> {
+        match state.rule_input.parse::<Rule>() {
             Err(error) => Err((path.to_owned(), error.into())),
-            Ok(valid_dead_alive) => Ok(valid_dead_alive),
+            Ok(rule) => Ok(rule),
         }
     }
 }
 
+struct DrawnInput {
+    dead: String,
+    alive: String,
+    automaton_chars: Vec<String>,
+    time: String,
+    selected_time: SelectedTime,
+    rule: String,
+    boundary: Boundary,
+    engine: EngineKind,
+    lenient: bool,
+    error: Option<String>,
+    /// Line of the loaded file which caused the error.
+    location: Option<SourceLocation>,
+}
+
 fn draw_path_and_chars_for_text(state: &mut OpenView, ui: &mut Ui) {
-    let mut dead_alive_input = (
-        String::default(),
-        String::default(),
-        String::default(),
-        SelectedTime::Seconds,
-        None,
-    );
+    let mut drawn_input = DrawnInput {
+        dead: String::default(),
+        alive: String::default(),
+        automaton_chars: Vec::new(),
+        time: String::default(),
+        selected_time: SelectedTime::Seconds,
+        rule: String::default(),
+        boundary: Boundary::default(),
+        engine: EngineKind::default(),
+        lenient: false,
+        error: None,
+        location: None,
+    };
 
     draw_utils::draw_grid(ui, "Input grid", |ui| {
-        dead_alive_input = match &state.game_file_state {
+        drawn_input = match &state.game_file_state {
             DataFileState::NotChoosen => {
                 draw_path_line(ui, MISSING_PATH_TXT, WARN_COLOR);
-                (
-                    state.dead_char_input.clone(),
-                    state.alive_char_input.clone(),
-                    state.time_interval.clone(),
-                    state.selected_time,
-                    None,
-                )
+                DrawnInput {
+                    dead: state.dead_char_input.clone(),
+                    alive: state.alive_char_input.clone(),
+                    automaton_chars: state.automaton_char_inputs.clone(),
+                    time: state.time_interval.clone(),
+                    selected_time: state.selected_time,
+                    rule: state.rule_input.clone(),
+                    boundary: state.boundary,
+                    engine: state.engine,
+                    lenient: state.lenient,
+                    error: None,
+                    location: None,
+                }
             }
             DataFileState::Choosen { path, .. } => {
                 draw_path_line(ui, &path.to_string_lossy(), NORMAL_COLOR);
@@ -171,25 +304,64 @@ fn draw_path_and_chars_for_text(state: &mut OpenView, ui: &mut Ui) {
         };
     });
 
-    let (dead, alive, time, selected_time, error) = dead_alive_input;
+    let DrawnInput {
+        dead,
+        alive,
+        automaton_chars,
+        time,
+        selected_time,
+        rule,
+        boundary,
+        engine,
+        lenient,
+        error,
+        location,
+    } = drawn_input;
 
     state.dead_char_input = dead;
     state.alive_char_input = alive;
+    state.automaton_char_inputs = automaton_chars;
     state.selected_time = selected_time;
     state.time_interval = time;
+    state.rule_input = rule;
+    state.boundary = boundary;
+    state.engine = engine;
+    state.lenient = lenient;
 
     if let Some(error_message) = error {
         draw_utils::computed_with_color(ui, error_message, ERR_COLOR);
     }
+    if let Some(location) = location {
+        draw_source_snippet(ui, &location);
+    }
 
-    fn draw_cell_fields(
-        state: &OpenView,
-        ui: &mut Ui,
-    ) -> (String, String, String, SelectedTime, Option<String>) {
-        let (dead, alive) = (
-            draw_input_single_line(ui, "Char dead cell:", state.dead_char_input.clone()),
-            draw_input_single_line(ui, "Char alive cell:", state.alive_char_input.clone()),
+    fn draw_cell_fields(state: &OpenView, ui: &mut Ui) -> DrawnInput {
+        let (mut dead, mut alive) = (
+            state.dead_char_input.clone(),
+            state.alive_char_input.clone(),
         );
+        let mut automaton_chars = state.automaton_char_inputs.clone();
+        match grid::built_in(&state.rule_input) {
+            Some(automaton) => {
+                let defaults = automaton.state_chars().iter().map(|&char| char.into());
+                if automaton_chars.len() != defaults.len() {
+                    automaton_chars = defaults.collect();
+                }
+                for (input, name) in automaton_chars.iter_mut().zip(automaton.state_names()) {
+                    let label = format!("Char {} cell:", name);
+                    *input = draw_input_single_line(ui, &label, input.clone());
+                }
+            }
+            None => {
+                dead = draw_input_single_line(ui, "Char dead cell:", dead);
+                alive = draw_input_single_line(ui, "Char alive cell:", alive);
+            }
+        }
+
+        let mut lenient = state.lenient;
+        ui.checkbox(&mut lenient, "Lenient plain text");
+        ui.label("Fills up short rows and skips comments, trailing whitespace and blank lines");
+        ui.end_row();
 
         let mut selected_time = state.selected_time;
         egui::ComboBox::from_label("Determine the used time unit")
@@ -200,13 +372,46 @@ fn draw_path_and_chars_for_text(state: &mut OpenView, ui: &mut Ui) {
             });
         ui.end_row();
 
-        return (
+        let time = draw_input_single_line(ui, "Time:", state.time_interval.clone());
+        let rule = draw_input_single_line(
+            ui,
+            "Rule (B/S/C, Larger than Life or Wireworld):",
+            state.rule_input.clone(),
+        );
+
+        let mut boundary = state.boundary;
+        egui::ComboBox::from_label("Determine how the edges are connected")
+            .selected_text(boundary.to_string())
+            .show_ui(ui, |ui| {
+                for option in Boundary::ALL {
+                    ui.selectable_value(&mut boundary, option, option.to_string());
+                }
+            });
+        ui.end_row();
+
+        let mut engine = state.engine;
+        egui::ComboBox::from_label("Determine the engine, unbounded engines ignore the boundary")
+            .selected_text(engine.to_string())
+            .show_ui(ui, |ui| {
+                for option in EngineKind::ALL {
+                    ui.selectable_value(&mut engine, option, option.to_string());
+                }
+            });
+        ui.end_row();
+
+        return DrawnInput {
             dead,
             alive,
-            draw_input_single_line(ui, "Time:", state.time_interval.clone()),
+            automaton_chars,
+            time,
             selected_time,
-            None,
-        );
+            rule,
+            boundary,
+            engine,
+            lenient,
+            error: None,
+            location: None,
+        };
 
         fn draw_input_single_line(ui: &mut Ui, label: &str, mut base: String) -> String {
             ui.label(label);
@@ -220,22 +425,52 @@ fn draw_path_and_chars_for_text(state: &mut OpenView, ui: &mut Ui) {
     fn draw_error_case(
         ui: &mut Ui,
         state: &OpenView,
-        error: &impl Error,
+        error: &ValidationError,
         path: &Path,
-    ) -> (String, String, String, SelectedTime, Option<String>) {
+    ) -> DrawnInput {
         draw_path_line(ui, &path.to_string_lossy(), ERR_COLOR);
         let to_return = draw_cell_fields(state, ui);
 
-        (
-            to_return.0,
-            to_return.1,
-            to_return.2,
-            to_return.3,
-            Some(error.to_string()),
-        )
+        DrawnInput {
+            error: Some(error.to_string()),
+            location: error.location().cloned(),
+            ..to_return
+        }
     }
 }
 
+/// Shows the line of the file which caused an error with the char at the column highlighted
+/// and a marker below it, so the problem does not have to be searched by hand.
+fn draw_source_snippet(ui: &mut Ui, location: &SourceLocation) {
+    let chars: Vec<char> = location.text.chars().collect();
+    let column = location.column - 1;
+    let before: String = chars.iter().take(column).collect();
+    // A missing char at the end of the line is shown as a highlighted space.
+    let highlighted = chars.get(column).map_or(String::from(" "), char::to_string);
+    let after: String = chars.iter().skip(column + 1).collect();
+    let gutter = format!("{} | ", location.line);
+
+    let format = |color: Color32, background: Color32| TextFormat {
+        font_id: FontId::monospace(SNIPPET_FONT_SIZE),
+        color,
+        background,
+        ..Default::default()
+    };
+    let mut line = LayoutJob::default();
+    line.append(&gutter, 0., format(Color32::GRAY, Color32::TRANSPARENT));
+    line.append(&before, 0., format(NORMAL_COLOR, Color32::TRANSPARENT));
+    line.append(&highlighted, 0., format(NORMAL_COLOR, ERR_COLOR));
+    line.append(&after, 0., format(NORMAL_COLOR, Color32::TRANSPARENT));
+    ui.label(line);
+
+    let marker = format!("{}^", " ".repeat(gutter.chars().count() + column));
+    ui.label(
+        RichText::new(marker)
+            .font(FontId::monospace(SNIPPET_FONT_SIZE))
+            .color(ERR_COLOR),
+    );
+}
+
 fn draw_path_line(ui: &mut Ui, message: &str, color: Color32) {
     draw_utils::computed_value(ui, "Path: ");
     draw_utils::computed_with_color(ui, message, color);
@@ -1,8 +1,9 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, sync::Arc};
 
 use getset::{CopyGetters, Getters};
 
-use crate::grid::Grid;
+use crate::engine::{Engine, EngineKind};
+use crate::grid::{Automaton, Boundary, Rule};
 
 use super::{SelectedTime, TimeUnit};
 
@@ -12,12 +13,29 @@ pub struct GatheredOpenViewData {
     pub alive_char_code: char,
     pub selected_time: SelectedTime,
     pub time_interval: TimeUnit,
-    pub game: Grid,
+    pub rule: Rule,
+    /// Replaces the rule if a hand written automaton was chosen.
+    pub automaton: Option<Arc<dyn Automaton>>,
+    /// Char of every state of the automaton by its number.
+    pub automaton_chars: Vec<char>,
+    pub boundary: Boundary,
+    pub engine: EngineKind,
+    /// Plain text files were read leniently.
+    pub lenient: bool,
+    pub game: Box<dyn Engine>,
     pub path: PathBuf,
 }
 
 impl GatheredOpenViewData {
-    pub fn clone_game(&self) -> Grid {
+    pub fn clone_game(&self) -> Box<dyn Engine> {
         self.game.clone()
     }
+
+    /// Char of every state by its number as given for the loaded file.
+    pub fn state_chars(&self) -> Vec<char> {
+        match self.automaton {
+            Some(_) => self.automaton_chars.clone(),
+            None => vec![self.dead_char_code, self.alive_char_code],
+        }
+    }
 }
@@ -22,16 +22,12 @@ impl From<TimeUnit> for Duration {
         }
     }
 }
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum SelectedTime {
+    #[default]
     Seconds,
     MsSeconds,
 }
-impl Default for SelectedTime {
-    fn default() -> Self {
-        Self::Seconds
-    }
-}
 impl Display for SelectedTime {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -2,7 +2,11 @@ use std::num::ParseIntError;
 
 use thiserror::Error;
 
-use crate::grid::{text_load_error::TextLoadError, InvalidCharCell};
+use crate::engine::InvalidEngine;
+use crate::grid::{
+    text_load_error::{SourceLocation, TextLoadError},
+    InvalidBoundary, InvalidCharCell, InvalidRule,
+};
 #[derive(Debug, Error)]
 pub enum ValidationError {
     #[error("{0}")]
@@ -11,6 +15,22 @@ pub enum ValidationError {
     NotNumberForTime,
     #[error("{0}")]
     InvalidChars(#[from] InvalidCharCell),
+    #[error("{0}")]
+    InvalidRule(#[from] InvalidRule),
+    #[error("{0}")]
+    InvalidBoundary(#[from] InvalidBoundary),
+    #[error("{0}")]
+    InvalidEngine(#[from] InvalidEngine),
+}
+
+impl ValidationError {
+    /// Place in the loaded file which caused the error.
+    pub fn location(&self) -> Option<&SourceLocation> {
+        match self {
+            Self::FailureInLoad(error) => error.location(),
+            _ => None,
+        }
+    }
 }
 
 impl From<ParseIntError> for ValidationError {
@@ -0,0 +1,332 @@
+use std::collections::{HashMap, HashSet};
+
+use eframe::egui::Ui;
+
+use crate::{
+    engine::{Engine, EngineKind, InvalidEngine},
+    grid::{
+        self, Boundary, CellBounds, CountConditions, GridDrawSettings, GridTransform, LifeCell,
+        Rule, TextData,
+    },
+};
+
+mod drawing;
+
+/// Cells per side of a chunk, one word holds a whole row of it.
+const CHUNK_SIZE: i64 = u64::BITS as i64;
+/// Every generation is calculated one by one like in a grid.
+const MAX_STEP_EXPONENT: u32 = 10;
+const EMPTY_CHUNK: Chunk = [0; CHUNK_SIZE as usize];
+
+/// Square of cells with one bit per cell, bit x of row y is the cell at column x.
+type Chunk = [u64; CHUNK_SIZE as usize];
+/// Row and column of a chunk. The chunk (1, 0) starts at the cell (64, 0).
+type ChunkKey = (i64, i64);
+
+/// Unbounded plane which only stores chunks with alive cells.
+/// Chunks are added when the pattern grows into them and removed once they are empty.
+#[derive(Clone)]
+pub struct SparseLife {
+    chunks: HashMap<ChunkKey, Chunk>,
+    rule: Rule,
+    drawing: GridDrawSettings,
+    passed_ticks: u64,
+    edited_cells: usize,
+    parallel: bool,
+}
+
+impl SparseLife {
+    pub fn new(
+        text: &TextData,
+        rule: Rule,
+        drawing: GridDrawSettings,
+    ) -> Result<Self, InvalidEngine> {
+        if rule.larger_than_life().is_some() {
+            return Err(InvalidEngine::LargerThanLife(rule));
+        }
+        if rule.is_hexagonal() {
+            return Err(InvalidEngine::HexagonalRule(rule));
+        }
+        if rule.is_birth(0) {
+            return Err(InvalidEngine::BirthWithoutNeighbours);
+        }
+        if rule.is_generations() {
+            return Err(InvalidEngine::GenerationsRule(rule));
+        }
+        if !rule.is_totalistic() {
+            return Err(InvalidEngine::NonTotalisticRule(rule));
+        }
+
+        let mut sparse = Self {
+            chunks: HashMap::new(),
+            rule,
+            drawing,
+            passed_ticks: 0,
+            edited_cells: 0,
+            parallel: false,
+        };
+        for (y, x) in grid::all_coords(text.height(), text.width()) {
+            if text.cell_at_y_x(y, x) == LifeCell::Alive {
+                sparse.set(y as i64, x as i64, true);
+            }
+        }
+
+        Ok(sparse)
+    }
+
+    fn set(&mut self, y: i64, x: i64, alive: bool) {
+        let (key, row, bit) = split(y, x);
+        let chunk = self.chunks.entry(key).or_insert(EMPTY_CHUNK);
+        if alive {
+            chunk[row] |= 1 << bit;
+        } else {
+            chunk[row] &= !(1 << bit);
+        }
+        if *chunk == EMPTY_CHUNK {
+            self.chunks.remove(&key);
+        }
+    }
+
+    /// Chunks with alive cells and their neighbours, which are the only ones able to change.
+    fn candidates(&self) -> Vec<ChunkKey> {
+        let candidates: HashSet<ChunkKey> = self
+            .chunks
+            .keys()
+            .flat_map(|&(chunk_y, chunk_x)| {
+                (-1..=1).flat_map(move |dy| (-1..=1).map(move |dx| (chunk_y + dy, chunk_x + dx)))
+            })
+            .collect();
+        candidates.into_iter().collect()
+    }
+
+    /// Calculates the next generation of the given chunks and leaves out the empty ones.
+    fn step_chunks(&self, keys: &[ChunkKey]) -> Vec<(ChunkKey, Chunk)> {
+        let conditions = self.rule.count_conditions();
+        keys.iter()
+            .map(|&key| (key, self.next_chunk(key, &conditions)))
+            .filter(|(_, chunk)| *chunk != EMPTY_CHUNK)
+            .collect()
+    }
+
+    fn next_chunk(&self, (chunk_y, chunk_x): ChunkKey, conditions: &CountConditions) -> Chunk {
+        let around = |dy: i64, dx: i64| {
+            self.chunks
+                .get(&(chunk_y + dy, chunk_x + dx))
+                .unwrap_or(&EMPTY_CHUNK)
+        };
+        let (above, current, below) = (
+            [around(-1, -1), around(-1, 0), around(-1, 1)],
+            [around(0, -1), around(0, 0), around(0, 1)],
+            [around(1, -1), around(1, 0), around(1, 1)],
+        );
+        let last = CHUNK_SIZE as usize - 1;
+
+        // Row y of the chunk is at y + 1 so the rows of the chunks above and below fit in.
+        let shifted = |y: usize| {
+            let [west, centre, east] = match y {
+                0 => above.map(|chunk| chunk[last]),
+                y if y == last + 2 => below.map(|chunk| chunk[0]),
+                y => current.map(|chunk| chunk[y - 1]),
+            };
+            (
+                (centre << 1) | (west >> last),
+                centre,
+                (centre >> 1) | ((east & 1) << last),
+            )
+        };
+
+        let mut next = EMPTY_CHUNK;
+        for (y, word) in next.iter_mut().enumerate() {
+            *word = grid::next_word_of([shifted(y), shifted(y + 1), shifted(y + 2)], conditions);
+        }
+        next
+    }
+}
+
+/// Returns the chunk, row in the chunk and column in the chunk of a cell.
+fn split(y: i64, x: i64) -> (ChunkKey, usize, usize) {
+    (
+        (y.div_euclid(CHUNK_SIZE), x.div_euclid(CHUNK_SIZE)),
+        y.rem_euclid(CHUNK_SIZE) as usize,
+        x.rem_euclid(CHUNK_SIZE) as usize,
+    )
+}
+
+impl Engine for SparseLife {
+    fn kind(&self) -> EngineKind {
+        EngineKind::Sparse
+    }
+
+    fn snapshot(&self) -> Box<dyn Engine> {
+        Box::new(self.clone())
+    }
+
+    fn keyframe_bytes(&self) -> usize {
+        self.chunks.len() * std::mem::size_of::<(ChunkKey, Chunk)>()
+    }
+
+    fn tick(&mut self) {
+        let candidates = self.candidates();
+        let next = if self.parallel {
+            let band = candidates.len().div_ceil(grid::available_threads()).max(1);
+            std::thread::scope(|scope| {
+                let handles: Vec<_> = candidates
+                    .chunks(band)
+                    .map(|keys| scope.spawn(|| self.step_chunks(keys)))
+                    .collect();
+                handles
+                    .into_iter()
+                    .flat_map(|handle| handle.join().unwrap())
+                    .collect()
+            })
+        } else {
+            self.step_chunks(&candidates).into_iter().collect()
+        };
+
+        self.chunks = next;
+        self.passed_ticks += 1;
+    }
+
+    fn max_step_exponent(&self) -> u32 {
+        MAX_STEP_EXPONENT
+    }
+
+    fn passed_tick(&self) -> u64 {
+        self.passed_ticks
+    }
+
+    fn population(&self) -> u64 {
+        self.chunks
+            .values()
+            .flatten()
+            .map(|word| word.count_ones() as u64)
+            .sum()
+    }
+
+    fn cell_at(&self, y: i64, x: i64) -> LifeCell {
+        let (key, row, bit) = split(y, x);
+        match self.chunks.get(&key) {
+            Some(chunk) if (chunk[row] >> bit) & 1 == 1 => LifeCell::Alive,
+            _ => LifeCell::Dead,
+        }
+    }
+
+    fn set_cell_at(&mut self, y: i64, x: i64, cell: LifeCell) {
+        if self.cell_at(y, x) != cell {
+            self.set(y, x, cell == LifeCell::Alive);
+            self.edited_cells += 1;
+        }
+    }
+
+    fn edited_cells(&self) -> usize {
+        self.edited_cells
+    }
+
+    fn bounds(&self) -> CellBounds {
+        let mut found: Option<(i64, i64, i64, i64)> = None;
+        for (&(chunk_y, chunk_x), chunk) in &self.chunks {
+            let is_alive = |word: &u64| *word != 0;
+            let (Some(first_row), Some(last_row)) = (
+                chunk.iter().position(is_alive),
+                chunk.iter().rposition(is_alive),
+            ) else {
+                continue;
+            };
+            let columns = chunk.iter().fold(0, |columns, word| columns | word);
+            let (top, left) = (chunk_y * CHUNK_SIZE, chunk_x * CHUNK_SIZE);
+            let chunk_bounds = (
+                top + first_row as i64,
+                left + columns.trailing_zeros() as i64,
+                top + last_row as i64,
+                left + (CHUNK_SIZE - 1 - columns.leading_zeros() as i64),
+            );
+
+            found = Some(match found {
+                Some((min_y, min_x, max_y, max_x)) => (
+                    min_y.min(chunk_bounds.0),
+                    min_x.min(chunk_bounds.1),
+                    max_y.max(chunk_bounds.2),
+                    max_x.max(chunk_bounds.3),
+                ),
+                None => chunk_bounds,
+            });
+        }
+
+        match found {
+            Some((min_y, min_x, max_y, max_x)) => CellBounds {
+                top: min_y,
+                left: min_x,
+                height: (max_y - min_y + 1) as u64,
+                width: (max_x - min_x + 1) as u64,
+            },
+            None => CellBounds::default(),
+        }
+    }
+
+    fn draw_at(&self, ui: &mut Ui, transform: GridTransform) {
+        SparseLife::draw_at(self, ui, transform);
+    }
+
+    fn drawing(&self) -> &GridDrawSettings {
+        &self.drawing
+    }
+
+    fn set_drawing(&mut self, drawing: GridDrawSettings) {
+        self.drawing = drawing;
+    }
+
+    fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    fn boundary(&self) -> Option<Boundary> {
+        None
+    }
+
+    fn set_parallel(&mut self, parallel: bool) {
+        self.parallel = parallel;
+    }
+
+    fn is_parallel(&self) -> bool {
+        self.parallel
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::{rle, Grid};
+
+    /// Acorn and a glider far apart, so several chunks are used and emptied again.
+    fn acorn_and_glider() -> TextData {
+        let text = "x = 300, y = 300, rule = B3/S23\n\
+                    150$150bo$152bo$149b2o2b3o46$60bo$61bo$59b3o!\n";
+        rle::parse(text).unwrap()
+    }
+
+    fn alive_cells(engine: &dyn Engine, size: i64) -> Vec<(i64, i64)> {
+        (0..size)
+            .flat_map(|y| (0..size).map(move |x| (y, x)))
+            .filter(|&(y, x)| engine.cell_at(y, x) == LifeCell::Alive)
+            .collect()
+    }
+
+    #[test]
+    fn steps_like_the_grid() {
+        let rule: Rule = "B36/S23".parse().unwrap();
+        let drawing = GridDrawSettings::default();
+        for parallel in [false, true] {
+            let mut sparse = SparseLife::new(&acorn_and_glider(), rule, drawing.clone()).unwrap();
+            sparse.set_parallel(parallel);
+            let mut grid = Grid::new(acorn_and_glider(), rule, Boundary::Plane, drawing.clone());
+            for generation in 0..80 {
+                sparse.tick();
+                grid.tick();
+                if generation % 10 == 9 {
+                    assert_eq!(sparse.population(), grid.population());
+                    assert_eq!(alive_cells(&sparse, 300), alive_cells(&grid, 300));
+                }
+            }
+        }
+    }
+}
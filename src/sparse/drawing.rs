@@ -0,0 +1,58 @@
+use eframe::{
+    egui::Ui,
+    epaint::{vec2, Mesh, Rect, Shape},
+};
+
+use super::{SparseLife, CHUNK_SIZE};
+use crate::grid::{renderer, GridTransform, LifeCell};
+
+impl SparseLife {
+    /// Draws the chunks inside of the clip rect as one mesh.
+    /// Neighbouring alive cells in a row are merged into one rectangle
+    /// and chunks of only a few pixels are drawn as a single square.
+    pub fn draw_at(&self, ui: &mut Ui, transform: GridTransform) {
+        let clip = ui.clip_rect();
+        let mut mesh = Mesh::default();
+        mesh.add_colored_rect(clip, self.drawing.color_for(LifeCell::Dead));
+
+        let color = self.drawing.color_for(LifeCell::Alive);
+        for (&(chunk_y, chunk_x), chunk) in &self.chunks {
+            let (top, left) = (chunk_y * CHUNK_SIZE, chunk_x * CHUNK_SIZE);
+            let rect = Rect::from_min_max(
+                transform.cell_min(top, left),
+                transform.cell_min(top + CHUNK_SIZE, left + CHUNK_SIZE),
+            );
+            if !clip.intersects(rect) {
+                continue;
+            }
+            if rect.width() <= 2. {
+                let rect = Rect::from_min_size(rect.min, rect.size().max(vec2(1., 1.)));
+                mesh.add_colored_rect(rect, color);
+                continue;
+            }
+
+            for (y, &word) in chunk.iter().enumerate() {
+                let y = top + y as i64;
+                let mut rest = word;
+                while rest != 0 {
+                    let start = rest.trailing_zeros();
+                    let length = (!(rest >> start)).trailing_zeros();
+                    let (x_start, x_end) = (left + start as i64, left + (start + length) as i64);
+                    let run = Rect::from_min_max(
+                        transform.cell_min(y, x_start),
+                        transform.cell_min(y + 1, x_end),
+                    );
+                    mesh.add_colored_rect(run, color);
+                    rest &= u64::MAX.checked_shl(start + length).unwrap_or(0);
+                }
+            }
+        }
+
+        let (min_y, min_x) = transform.cell_at_position(clip.min);
+        let (max_y, max_x) = transform.cell_at_position(clip.max);
+        let lines = (min_y..max_y + 1, min_x..max_x + 1);
+        renderer::add_grid_lines(&mut mesh, transform, lines, self.drawing.stroke_color);
+
+        ui.painter().add(Shape::mesh(mesh));
+    }
+}
@@ -17,10 +17,6 @@ impl Timer {
         }
     }
 
-    pub fn interval_as_ms(&self) -> u128 {
-        self.interval.as_millis()
-    }
-
     pub fn resume(&mut self) {
         if !self.paused {
             return;
@@ -46,20 +42,21 @@ impl Timer {
         self.last = Instant::now();
     }
 
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Time until the interval is up. Zero if it is already up.
+    pub fn remaining(&self) -> Duration {
+        self.interval.saturating_sub(self.passed())
+    }
+
     pub fn is_up(&mut self) -> bool {
         if self.paused {
             return false;
         }
 
-        let passed = {
-            let mut passed = self.last.elapsed();
-            if let Some(from_pause) = self.paused_elapsed {
-                passed += from_pause;
-            }
-
-            passed
-        };
-        let is_up = passed >= self.interval;
+        let is_up = self.passed() >= self.interval;
 
         if is_up {
             self.paused_elapsed = None;
@@ -68,4 +65,13 @@ impl Timer {
             false
         }
     }
+
+    fn passed(&self) -> Duration {
+        let mut passed = self.last.elapsed();
+        if let Some(from_pause) = self.paused_elapsed {
+            passed += from_pause;
+        }
+
+        passed
+    }
 }